@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::thread;
+
+use crate::intcode::{self, Machine, ScheduleError, Scheduler};
+
+/// Copies of one Intcode program wired together, each amplifier sending its
+/// outputs to every amplifier connected after it. Amplifiers are numbered in
+/// the order they're added.
+#[derive(Clone, Debug)]
+pub struct AmplifierCircuit {
+    program: Vec<i64>,
+    phases: Vec<i64>,
+    edges: Vec<Vec<usize>>,
+    inputs: Vec<Vec<i64>>,
+    output: Option<usize>,
+}
+
+impl AmplifierCircuit {
+    pub fn new(program: Vec<i64>) -> Self {
+        AmplifierCircuit {
+            program,
+            phases: Vec::new(),
+            edges: Vec::new(),
+            inputs: Vec::new(),
+            output: None,
+        }
+    }
+
+    /// Amplifiers with the given phases, each feeding the next, with the
+    /// signal 0 going into the first and coming out of the last.
+    pub fn chain(program: Vec<i64>, phases: &[i64]) -> Self {
+        let mut circuit = AmplifierCircuit::new(program);
+        let amplifiers: Vec<usize> = phases
+            .iter()
+            .map(|&phase| circuit.add_amplifier(phase))
+            .collect();
+        for pair in amplifiers.windows(2) {
+            circuit.connect(pair[0], pair[1]);
+        }
+        if let (Some(&first), Some(&last)) = (amplifiers.first(), amplifiers.last()) {
+            circuit.feed(first, 0);
+            circuit.set_output(last);
+        }
+        circuit
+    }
+
+    /// A chain with the last amplifier feeding back into the first.
+    pub fn ring(program: Vec<i64>, phases: &[i64]) -> Self {
+        let mut circuit = AmplifierCircuit::chain(program, phases);
+        if let Some(last) = circuit.output {
+            circuit.connect(last, 0);
+        }
+        circuit
+    }
+
+    /// Adds an amplifier whose first input is `phase`.
+    pub fn add_amplifier(&mut self, phase: i64) -> usize {
+        self.phases.push(phase);
+        self.edges.push(Vec::new());
+        self.inputs.push(Vec::new());
+        self.phases.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.phases.len(), "no amplifier {}", to);
+        self.edges[from].push(to);
+    }
+
+    /// Queues a value for an amplifier to read after its phase.
+    pub fn feed(&mut self, amplifier: usize, value: i64) {
+        self.inputs[amplifier].push(value);
+    }
+
+    /// Picks the amplifier whose last output is the circuit's signal.
+    pub fn set_output(&mut self, amplifier: usize) {
+        assert!(amplifier < self.phases.len(), "no amplifier {}", amplifier);
+        self.output = Some(amplifier);
+    }
+
+    pub fn len(&self) -> usize {
+        self.phases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phases.is_empty()
+    }
+
+    /// Runs the amplifiers in turn until every one has halted, and gives the
+    /// last signal from the output amplifier, if it sent any.
+    ///
+    /// Amplifiers left waiting for input that no running amplifier will send
+    /// are reported as a deadlock.
+    pub fn run(&self) -> Result<Option<i64>, ScheduleError> {
+        let mut scheduler = Scheduler::new();
+        for (amplifier, &phase) in self.phases.iter().enumerate() {
+            scheduler.add(Machine::new(self.program.clone()));
+            scheduler.send(amplifier, phase);
+            for &value in &self.inputs[amplifier] {
+                scheduler.send(amplifier, value);
+            }
+        }
+        for (from, targets) in self.edges.iter().enumerate() {
+            for &to in targets {
+                scheduler.connect(from, to);
+            }
+        }
+
+        scheduler.run()?;
+        Ok(self.output.and_then(|output| scheduler.outputs(output).last().copied()))
+    }
+}
+
+/// Every ordering of some items, by Heap's algorithm, so that each one
+/// differs from the one before by a single swap.
+#[derive(Clone, Debug)]
+pub struct Permutations<T> {
+    items: Vec<T>,
+    counters: Vec<usize>,
+    index: usize,
+    started: bool,
+}
+
+pub fn permutations<T: Clone>(items: &[T]) -> Permutations<T> {
+    Permutations {
+        items: items.to_vec(),
+        counters: vec![0; items.len()],
+        index: 1,
+        started: false,
+    }
+}
+
+impl<T: Clone> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if !self.started {
+            self.started = true;
+            return Some(self.items.clone());
+        }
+
+        while self.index < self.items.len() {
+            let i = self.index;
+            if self.counters[i] < i {
+                let j = if i.is_multiple_of(2) { 0 } else { self.counters[i] };
+                self.items.swap(j, i);
+                self.counters[i] += 1;
+                self.index = 1;
+                return Some(self.items.clone());
+            }
+            self.counters[i] = 0;
+            self.index += 1;
+        }
+        None
+    }
+}
+
+/// Runs one amplifier on its own, giving its last output.
+fn amplify(program: &[i64], phase: i64, signal: i64) -> Result<Option<i64>, intcode::Error> {
+    let mut last = None;
+    Machine::new(program.to_vec()).run(
+        [phase, signal].iter().copied(),
+        |value| last = Some(value),
+    )?;
+    Ok(last)
+}
+
+// Signals coming out of a chain of amplifiers, by the phases of the chain.
+type Cache = HashMap<Vec<i64>, Option<i64>>;
+
+/// Looks for the ordering of a set of phases that gives the strongest
+/// signal.
+#[derive(Clone, Debug)]
+pub struct PhaseSearch {
+    program: Vec<i64>,
+    phases: Vec<i64>,
+    threads: usize,
+}
+
+impl PhaseSearch {
+    pub fn new(program: Vec<i64>, phases: &[i64]) -> Self {
+        PhaseSearch { program, phases: phases.to_vec(), threads: 1 }
+    }
+
+    /// Splits the orderings between this many threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The best ordering for a chain of amplifiers, and its signal.
+    ///
+    /// The signal after the first few amplifiers of a chain doesn't depend
+    /// on the rest, so it's remembered for each run of phases the chain
+    /// starts with. Heap's algorithm changes the end of its orderings least
+    /// often, so they're read backwards to make those runs repeat.
+    pub fn best_chain(&self) -> Result<Option<(Vec<i64>, i64)>, ScheduleError> {
+        self.search(|phases, cache| {
+            let mut signal = Some(0);
+            for len in 1 ..= phases.len() {
+                signal = match cache.get(&phases[..len]) {
+                    Some(&cached) => cached,
+                    None => {
+                        let next = match signal {
+                            Some(signal) => amplify(&self.program, phases[len - 1], signal)
+                                .map_err(|error| ScheduleError::Failed { machine: len - 1, error })?,
+                            None => None,
+                        };
+                        cache.insert(phases[..len].to_vec(), next);
+                        next
+                    },
+                };
+            }
+            Ok(signal)
+        })
+    }
+
+    /// The best ordering for a ring of amplifiers, and its signal.
+    pub fn best_ring(&self) -> Result<Option<(Vec<i64>, i64)>, ScheduleError> {
+        self.search(|phases, _| AmplifierCircuit::ring(self.program.clone(), phases).run())
+    }
+
+    fn search<F>(&self, evaluate: F) -> Result<Option<(Vec<i64>, i64)>, ScheduleError>
+    where
+        F: Fn(&[i64], &mut Cache) -> Result<Option<i64>, ScheduleError> + Sync,
+    {
+        let orderings: Vec<Vec<i64>> = permutations(&self.phases)
+            .map(|mut phases| {
+                phases.reverse();
+                phases
+            })
+            .collect();
+
+        // Each part of the work gives the index of its best ordering.
+        let best = |start: usize, orderings: &[Vec<i64>]| {
+            let mut cache = Cache::new();
+            let mut best: Option<(usize, i64)> = None;
+            for (i, phases) in orderings.iter().enumerate() {
+                if let Some(signal) = evaluate(phases, &mut cache)? {
+                    if best.is_none_or(|(_, best)| signal > best) {
+                        best = Some((start + i, signal));
+                    }
+                }
+            }
+            Ok(best)
+        };
+
+        let chunk = orderings.len().div_ceil(self.threads).max(1);
+        let results: Vec<Result<_, ScheduleError>> = if self.threads == 1 {
+            vec![best(0, &orderings)]
+        } else {
+            let best = &best;
+            thread::scope(|scope| {
+                let handles: Vec<_> = orderings
+                    .chunks(chunk)
+                    .enumerate()
+                    .map(|(i, orderings)| scope.spawn(move || best(i * chunk, orderings)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("phase search panicked"))
+                    .collect()
+            })
+        };
+
+        // Ties go to the earliest ordering, however the work was split.
+        let mut overall: Option<(usize, i64)> = None;
+        for result in results {
+            if let Some((i, signal)) = result? {
+                if overall.is_none_or(|(_, best)| signal > best) {
+                    overall = Some((i, signal));
+                }
+            }
+        }
+        Ok(overall.map(|(i, signal)| (orderings[i].clone(), signal)))
+    }
+}
@@ -1,33 +1,312 @@
-use std::iter;
-use std::num::NonZeroU64;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
 use aoc_runner_derive::aoc;
+use num::Integer;
 
-fn get_fuel(mass: u64) -> Option<u64> {
-    (mass / 3)
-        .checked_sub(2)
-        .and_then(|n| NonZeroU64::new(n))
-        .map(|n| n.get())
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidMass { line: usize, text: String },
+    ZeroDivisor,
+    /// Some mass needs at least as much fuel as it weighs, so the fuel for
+    /// the fuel would never run out.
+    Unbounded,
 }
 
-#[aoc(day1, part1)]
-fn part1(input: &str) -> u64 {
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidMass { line, text } =>
+                write!(f, "line {}: invalid mass {:?}", line, text),
+            Error::ZeroDivisor => write!(f, "the divisor must be positive"),
+            Error::Unbounded =>
+                write!(f, "a mass of 1 needs fuel, so fuel for fuel never ends"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Reads one mass per line. Lines are numbered from 1.
+pub fn parse_masses<T: FromStr>(input: &str) -> Result<Vec<T>, Error> {
     input
         .lines()
-        .map(|line| get_fuel(line.parse().unwrap()).unwrap())
+        .enumerate()
+        .map(|(i, line)| {
+            line.trim().parse().map_err(|_| Error::InvalidMass {
+                line: i + 1,
+                text: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+    /// Halves round up.
+    Nearest,
+}
+
+/// Fuel for a mass is the mass divided by `divisor`, rounded, less `offset`,
+/// and never below zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FuelModel {
+    divisor: u64,
+    offset: u64,
+    rounding: Rounding,
+}
+
+impl Default for FuelModel {
+    /// The puzzle's model: a third of the mass, rounded down, less two.
+    fn default() -> Self {
+        FuelModel { divisor: 3, offset: 2, rounding: Rounding::Down }
+    }
+}
+
+impl FuelModel {
+    pub fn new(divisor: u64, offset: u64, rounding: Rounding) -> Result<Self, Error> {
+        if divisor == 0 {
+            return Err(Error::ZeroDivisor);
+        }
+
+        // Every mass needs less fuel than it weighs unless a mass of 1 does,
+        // which only happens without an offset.
+        let model = FuelModel { divisor, offset, rounding };
+        if model.fuel(&1u64) > 0 {
+            return Err(Error::Unbounded);
+        }
+        Ok(model)
+    }
+
+    pub fn fuel<T>(&self, mass: &T) -> T
+    where
+        T: Integer + Clone + From<u64>,
+    {
+        let divisor = T::from(self.divisor);
+        let (quotient, remainder) = mass.div_rem(&divisor);
+        let rounded = match self.rounding {
+            Rounding::Down => quotient,
+            Rounding::Up if remainder.is_zero() => quotient,
+            Rounding::Up => quotient + T::one(),
+            Rounding::Nearest if remainder.clone() + remainder >= divisor =>
+                quotient + T::one(),
+            Rounding::Nearest => quotient,
+        };
+
+        let offset = T::from(self.offset);
+        if rounded > offset { rounded - offset } else { T::zero() }
+    }
+
+    /// The fuel for a mass plus the fuel for that fuel, and so on. Each step
+    /// divides the mass, so this takes logarithmically many steps even for
+    /// masses far beyond `u64`. A divisor of 1 only takes the offset off
+    /// each time, so the fuel is summed as an arithmetic series instead.
+    pub fn total_fuel<T>(&self, mass: &T) -> T
+    where
+        T: Integer + Clone + From<u64>,
+    {
+        if self.divisor == 1 {
+            return self.series(mass);
+        }
+
+        let mut total = T::zero();
+        let mut fuel = self.fuel(mass);
+        while !fuel.is_zero() {
+            let next = self.fuel(&fuel);
+            total = total + fuel;
+            fuel = next;
+        }
+        total
+    }
+
+    // The sum of `mass - k * offset` over every k from 1 that leaves it
+    // positive. `new` makes sure the offset isn't zero.
+    fn series<T>(&self, mass: &T) -> T
+    where
+        T: Integer + Clone + From<u64>,
+    {
+        if mass.is_zero() {
+            return T::zero();
+        }
+        let offset = T::from(self.offset);
+        let count = (mass.clone() - T::one()) / offset.clone();
+        if count.is_zero() {
+            return T::zero();
+        }
+        let first = mass.clone() - offset.clone();
+        let last = mass.clone() - offset * count.clone();
+        let two = T::one() + T::one();
+        // Halving whichever factor is even keeps the product from
+        // overflowing any sooner than the sum itself.
+        if count.is_even() {
+            count / two * (first + last)
+        } else {
+            count * ((first + last) / two)
+        }
+    }
+
+    /// Works out the fuel for every module. Modules' fuel chains soon reach
+    /// masses that other modules have needed, so totals are remembered along
+    /// the way.
+    pub fn breakdown<T>(&self, masses: &[T]) -> Breakdown<T>
+    where
+        T: Integer + Clone + From<u64> + Hash,
+    {
+        let mut totals: HashMap<T, T> = HashMap::new();
+        let modules = masses
+            .iter()
+            .map(|mass| {
+                if self.divisor == 1 {
+                    return ModuleFuel {
+                        mass: mass.clone(),
+                        fuel: self.fuel(mass),
+                        total: self.series(mass),
+                    };
+                }
+
+                let mut chain = Vec::new();
+                let mut current = mass.clone();
+                let mut total = T::zero();
+                while !current.is_zero() {
+                    if let Some(known) = totals.get(&current) {
+                        total = known.clone();
+                        break;
+                    }
+                    let fuel = self.fuel(&current);
+                    chain.push((current, fuel.clone()));
+                    current = fuel;
+                }
+                for (mass, fuel) in chain.into_iter().rev() {
+                    total = total + fuel;
+                    totals.insert(mass, total.clone());
+                }
+
+                ModuleFuel {
+                    mass: mass.clone(),
+                    fuel: self.fuel(mass),
+                    total: totals.get(mass).cloned().unwrap_or_else(T::zero),
+                }
+            })
+            .collect();
+
+        Breakdown { modules }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleFuel<T> {
+    pub mass: T,
+    /// The fuel for the module alone.
+    pub fuel: T,
+    /// The fuel including the fuel for the fuel.
+    pub total: T,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breakdown<T> {
+    pub modules: Vec<ModuleFuel<T>>,
+}
+
+impl<T: Integer + Clone> Breakdown<T> {
+    pub fn fuel(&self) -> T {
+        self.modules.iter().fold(T::zero(), |sum, module| sum + module.fuel.clone())
+    }
+
+    pub fn total(&self) -> T {
+        self.modules.iter().fold(T::zero(), |sum, module| sum + module.total.clone())
+    }
+}
+
+impl<T: Integer + Clone + fmt::Display> fmt::Display for Breakdown<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>6} {:>16} {:>16} {:>16}", "module", "mass", "fuel", "total")?;
+        for (i, module) in self.modules.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>6} {:>16} {:>16} {:>16}",
+                i + 1, module.mass, module.fuel, module.total,
+            )?;
+        }
+        write!(f, "{:>6} {:>16} {:>16} {:>16}", "", "", self.fuel(), self.total())
+    }
+}
+
+#[aoc(day1, part1)]
+fn part1(input: &str) -> u128 {
+    let model = FuelModel::default();
+    parse_masses::<u128>(input)
+        .unwrap()
+        .iter()
+        .map(|mass| model.fuel(mass))
         .sum()
 }
 
 #[aoc(day1, part2)]
-fn part2(input: &str) -> u64 {
-    input
-        .lines()
-        .map(|line| {
-            let module: u64 = line.parse().unwrap();
-            iter::successors(
-                    get_fuel(module),
-                    |&fuel| get_fuel(fuel),
-                )
-                .sum::<u64>()
-        })
+fn part2(input: &str) -> u128 {
+    let model = FuelModel::default();
+    parse_masses::<u128>(input)
+        .unwrap()
+        .iter()
+        .map(|mass| model.total_fuel(mass))
         .sum()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzle_examples() {
+        let model = FuelModel::default();
+        let examples: [(u64, u64, u64); 4] =
+            [(12, 2, 2), (14, 2, 2), (1969, 654, 966), (100756, 33583, 50346)];
+        for &(mass, fuel, total) in &examples {
+            assert_eq!(model.fuel(&mass), fuel);
+            assert_eq!(model.total_fuel(&mass), total);
+        }
+        assert_eq!(part2("12\n1969\n100756\n"), 2 + 966 + 50346);
+    }
+
+    #[test]
+    fn reports_invalid_masses() {
+        assert_eq!(
+            parse_masses::<u64>("12\n1x9\n"),
+            Err(Error::InvalidMass { line: 2, text: "1x9".to_string() }),
+        );
+        assert_eq!(
+            parse_masses::<u64>("-4"),
+            Err(Error::InvalidMass { line: 1, text: "-4".to_string() }),
+        );
+    }
+
+    #[test]
+    fn validates_models() {
+        assert_eq!(FuelModel::new(0, 2, Rounding::Down), Err(Error::ZeroDivisor));
+        assert_eq!(FuelModel::new(1, 0, Rounding::Down), Err(Error::Unbounded));
+        assert_eq!(FuelModel::new(2, 0, Rounding::Up), Err(Error::Unbounded));
+        assert!(FuelModel::new(2, 0, Rounding::Down).is_ok());
+    }
+
+    #[test]
+    fn divisor_one_sums_in_closed_form() {
+        for offset in 1..5 {
+            let model = FuelModel::new(1, offset, Rounding::Down).unwrap();
+            for mass in 0..50u64 {
+                let expected: u64 = (1..)
+                    .map(|k| mass as i64 - (k * offset) as i64)
+                    .take_while(|&fuel| fuel > 0)
+                    .sum::<i64>() as u64;
+                assert_eq!(model.total_fuel(&mass), expected);
+                assert_eq!(model.breakdown(&[mass]).total(), expected);
+            }
+        }
+
+        let model = FuelModel::new(1, 1, Rounding::Down).unwrap();
+        let mass: u128 = 1 << 62;
+        assert_eq!(model.total_fuel(&mass), mass * (mass - 1) / 2);
+    }
+}
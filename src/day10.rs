@@ -1,112 +1,83 @@
+use std::cmp::Ordering;
 use std::iter;
-use std::ops::Range;
 use std::collections::{HashSet, VecDeque};
-use std::f64::consts::{PI, FRAC_PI_2};
 use linked_hash_map::LinkedHashMap;
 use num::Integer;
 use aoc_runner_derive::aoc;
 
-fn parse_input(input: &str) -> (HashSet<(i64, i64)>, Range<i64>, Range<i64>) {
-    let mut max_x = 0;
-    let mut max_y = 0;
-
-    let set = input
-        .lines()
-        .zip(0..)
-        .flat_map(|(line, y)| {
-            line
-                .bytes()
-                .zip(0..)
-                .filter_map(move |(byte, x)| {
-                    if byte == b'#' {
-                        Some((x, y))
-                    } else { None }
-                })
-        })
-        .inspect(|&(x, y)| {
-            if x > max_x { max_x = x };
-            if y > max_y { max_y = y };
-        })
-        .collect();
-    
-    (set, 0 .. max_x + 1, 0 .. max_y + 1)
+use crate::geometry::Point;
+use crate::grid::{Grid, SparseGrid};
+
+type Ray = (Point, VecDeque<Point>);
+
+fn parse_input(input: &str) -> SparseGrid<()> {
+    SparseGrid::parse(input, |c| if c == '#' { Some(()) } else { None })
+}
+
+/// Orders directions clockwise from straight up, with y growing downwards.
+/// Directions are split into the half from up to just before down and the
+/// half from down to just before up, and within a half the sign of the cross
+/// product says which comes first, so no angle is ever worked out.
+fn clockwise(a: Point, b: Point) -> Ordering {
+    let half = |p: Point| if p.x > 0 || (p.x == 0 && p.y < 0) { 0 } else { 1 };
+    half(a)
+        .cmp(&half(b))
+        .then_with(|| 0.cmp(&(a.x * b.y - a.y * b.x)))
 }
 
 fn sort_asteroids(
-    asteroids: &HashSet<(i64, i64)>,
-    (x, y): (i64, i64),
-    x_range: Range<i64>,
-    y_range: Range<i64>,
-) -> Vec<((i64, i64), VecDeque<(i64, i64)>, f64)> {
+    asteroids: &SparseGrid<()>,
+    station: Point,
+) -> Vec<Ray> {
+    let bounds = asteroids.bounds().unwrap();
     let mut checked: HashSet<_> = HashSet::new();
 
     asteroids
-        .iter()
-        .filter_map(|&(x2, y2)| {
-            let (dx, dy) = (x2 - x, y2 - y);
+        .positions()
+        .filter_map(|asteroid| {
+            let Point { x: dx, y: dy } = asteroid - station;
             let div = dx.gcd(&dy);
             if div == 0 { return None };
-            let (dx, dy) = (dx / div, dy / div);
-            if checked.insert((dx, dy)) {
+            let ray = Point::new(dx / div, dy / div);
+            if checked.insert(ray) {
                 let intersected =
                     iter::successors(
-                        Some((x + dx, y + dy)),
-                        |(x, y)| {
-                            let (x, y) = (x + dx, y + dy);
-                            if x_range.contains(&x) && y_range.contains(&y) {
-                                Some((x, y))
-                            } else { None }
-                        }
+                        Some(station + ray),
+                        |&pos| Some(pos + ray).filter(|&pos| bounds.contains(pos)),
                     )
-                    .filter(|pos| asteroids.contains(pos))
+                    .filter(|&pos| asteroids.contains(pos))
                     .fold(VecDeque::new(), |mut intersected, pos| {
                         intersected.push_back(pos);
                         intersected
                     });
-                let mut angle = (-dy as f64).atan2(dx as f64) - FRAC_PI_2;
-                if angle > 0.0 {
-                    angle = angle - 2.0*PI;
-                }
-                Some(((dx, dy), intersected, angle))
+                Some((ray, intersected))
             } else { None }
         })
         .collect()
 }
 
-fn best_station(
-    asteroids: &HashSet<(i64, i64)>,
-    x_range: Range<i64>,
-    y_range: Range<i64>,
-) -> Vec<((i64, i64), VecDeque<(i64, i64)>, f64)> {
+fn best_station(asteroids: &SparseGrid<()>) -> Vec<Ray> {
     asteroids
-        .iter()
-        .map(|&asteroid| {
-            sort_asteroids(
-                asteroids,
-                asteroid,
-                x_range.clone(),
-                y_range.clone(),
-            )
-        })
+        .positions()
+        .map(|asteroid| sort_asteroids(asteroids, asteroid))
         .max_by_key(|n| n.len())
         .unwrap()
 }
 
 #[aoc(day10, part1)]
 fn part1(input: &str) -> usize {
-    let (asteroids, x_range, y_range) = parse_input(input);
-    best_station(&asteroids, x_range, y_range).len()
+    let asteroids = parse_input(input);
+    best_station(&asteroids).len()
 }
 
 #[aoc(day10, part2)]
 fn part2(input: &str) -> i64 {
-    let (asteroids, x_range, y_range) = parse_input(input);
-    let mut asteroid_angles = best_station(&asteroids, x_range, y_range);
-    asteroid_angles.sort_by(|(_, _, a), (_, _, b)| b.partial_cmp(a).unwrap());
+    let asteroids = parse_input(input);
+    let mut rays = best_station(&asteroids);
+    rays.sort_by(|&(a, _), &(b, _)| clockwise(a, b));
 
-    let mut by_ray: LinkedHashMap<_, _> = asteroid_angles
+    let mut by_ray: LinkedHashMap<_, _> = rays
         .into_iter()
-        .map(|(ray, asteroids, _)| (ray, asteroids))
         .collect();
     
     let mut count = 0;
@@ -115,7 +86,7 @@ fn part2(input: &str) -> i64 {
             count += 1;
 
             let asteroids = entry.get_mut();
-            let (x, y) = asteroids.pop_front().unwrap();
+            let Point { x, y } = asteroids.pop_front().unwrap();
             if count == 200 {
                 return x * 100 + y;
             } else if asteroids.is_empty() {
@@ -126,3 +97,93 @@ fn part2(input: &str) -> i64 {
 
     0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LARGE_EXAMPLE: &str = "\
+.#..##.###...#######
+##.############..##.
+.#.######.########.#
+.###.#######.####.#.
+#####.##.#.##.###.##
+..#####..#.#########
+####################
+#.####....###.#.#.##
+##.#################
+#####.##.###..####..
+..######..##.#######
+####.##.####...##..#
+.#####..#.######.###
+##...#.##########...
+#.##########.#######
+.####.#.###.###.#.##
+....##.##.###..#####
+.#.#.###########.###
+#.#.#.#####.####.###
+###.##.####.##.#..##";
+
+    fn sorted(mut rays: Vec<Point>) -> Vec<Point> {
+        rays.sort_by(|&a, &b| clockwise(a, b));
+        rays
+    }
+
+    #[test]
+    fn axes_go_clockwise_from_up() {
+        let up = Point::new(0, -1);
+        let right = Point::new(1, 0);
+        let down = Point::new(0, 1);
+        let left = Point::new(-1, 0);
+        assert_eq!(sorted(vec![left, down, right, up]), vec![up, right, down, left]);
+        assert_eq!(clockwise(up, up), Ordering::Equal);
+        assert_eq!(clockwise(left, up), Ordering::Greater);
+    }
+
+    #[test]
+    fn orders_near_collinear_rays() {
+        // The rays of each pair are about 1e-12 radians apart, one pair in
+        // each quadrant.
+        let pairs = [
+            (Point::new(1_000_000, -999_999), Point::new(999_999, -999_998)),
+            (Point::new(999_999, 1_000_000), Point::new(999_998, 999_999)),
+            (Point::new(-1_000_000, 999_999), Point::new(-999_999, 999_998)),
+            (Point::new(-999_999, -1_000_000), Point::new(-999_998, -999_999)),
+        ];
+        for &(a, b) in &pairs {
+            assert_eq!(clockwise(a, b), Ordering::Less, "{} before {}", a, b);
+            assert_eq!(clockwise(b, a), Ordering::Greater, "{} after {}", b, a);
+        }
+
+        // Just either side of straight up.
+        let left_of_up = Point::new(-1, -1_000_000_000);
+        let right_of_up = Point::new(1, -1_000_000_000);
+        assert_eq!(
+            sorted(vec![left_of_up, Point::new(0, -1), right_of_up]),
+            vec![Point::new(0, -1), right_of_up, left_of_up],
+        );
+    }
+
+    #[test]
+    fn matches_angles_for_small_rays() {
+        let rays: Vec<Point> = (-12..=12i64)
+            .flat_map(|x| (-12..=12i64).map(move |y| Point::new(x, y)))
+            .filter(|p| p.x.gcd(&p.y) == 1)
+            .collect();
+        let angle = |p: &Point| {
+            let angle = (p.x as f64).atan2(-p.y as f64);
+            if angle < 0.0 { angle + 2.0 * std::f64::consts::PI } else { angle }
+        };
+
+        let mut by_angle = rays.clone();
+        by_angle.sort_by(|a, b| angle(a).partial_cmp(&angle(b)).unwrap());
+        assert_eq!(sorted(rays), by_angle);
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(part1(".#..#\n.....\n#####\n....#\n...##"), 8);
+        assert_eq!(part1(LARGE_EXAMPLE), 210);
+        assert_eq!(part2(LARGE_EXAMPLE), 802);
+    }
+}
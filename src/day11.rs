@@ -1,137 +1,106 @@
-use std::collections::HashMap;
-use std::sync::mpsc;
-use std::thread;
-use std::iter;
 use std::fmt;
+use std::io;
 use aoc_runner_derive::aoc;
 
+use crate::geometry::{Point, Turn};
+use crate::grid::{Grid, SparseGrid};
+use crate::image::Image;
 use crate::intcode;
+use crate::ocr;
+use crate::robot::{Panel, PlayOptions, Protocol, Replay, Robot, RobotController};
 
-#[derive(Clone, Copy)]
-enum Direction {
-    Right,
-    Up,
-    Left,
-    Down,
+struct HullPainter {
+    start_color: i64,
 }
 
-impl Direction {
-    fn right(&mut self) {
-        *self = match self {
-            Direction::Right => Direction::Down,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-            Direction::Up => Direction::Right,
-        }
+impl Protocol for HullPainter {
+    fn command_len(&self) -> usize {
+        2
     }
 
-    fn left(&mut self) {
-        *self = match self {
-            Direction::Right => Direction::Up,
-            Direction::Up => Direction::Left,
-            Direction::Left => Direction::Down,
-            Direction::Down => Direction::Right,
-        }
+    fn sense(&mut self, robot: &Robot) -> i64 {
+        robot.world
+            .color(robot.position)
+            .unwrap_or(if robot.position == Point::ORIGIN { self.start_color } else { 0 })
     }
 
-    fn apply(self, pos: &mut (i64, i64)) {
-        match self {
-            Direction::Right => pos.0 += 1,
-            Direction::Up => pos.1 -= 1,
-            Direction::Left => pos.0 -= 1,
-            Direction::Down => pos.1 += 1,
-        }
+    fn act(&mut self, robot: &mut Robot, command: &[i64]) {
+        robot.paint(command[0]);
+        robot.turn(Turn::from_code(command[1]).expect("invalid turn"));
+        robot.forward();
     }
 }
 
+/// Runs the hull painting robot, returning it with the record of its run so
+/// that it can be inspected or replayed.
+pub fn hull_robot(program: Vec<i64>, start_white: bool) -> Robot {
+    let mut controller = RobotController::new(
+        program,
+        HullPainter { start_color: start_white as i64 },
+    );
+    controller.run().unwrap();
+    controller.into_robot()
+}
+
+/// Runs the robot from the puzzle input and replays its run on the terminal.
+/// Part 2's run starts on a white panel.
+pub fn replay(input: &str, start_white: bool, options: PlayOptions) -> io::Result<()> {
+    let robot = hull_robot(intcode::parse_program(input).unwrap(), start_white);
+    Replay::new(&robot.world).play(options)
+}
+
+/// The painted hull with unpainted panels left transparent, for exporting
+/// with the Space Image Format palette.
+pub fn hull_image(robot: &Robot) -> Image {
+    let panels = &robot.world.panels;
+    Image::from_rows(panels.rows(|panel| {
+        panel
+            .and_then(Panel::color)
+            .map_or(2, |color| color as u8)
+    }))
+}
+
 fn paint_panels(
-    mut program: Vec<i64>,
+    program: Vec<i64>,
     start_white: bool,
-) -> HashMap<(i64, i64), bool> {
-    let (input, rx) = mpsc::sync_channel(0);
-    let (tx, output) = mpsc::sync_channel(0);
-
-    thread::spawn(move || {
-        intcode::execute(
-            &mut program,
-            rx.iter(),
-            move |value| tx.send(value).unwrap(),
-        );
-    });
-
-    let mut position = (0, 0);
-    let mut direction = Direction::Up;
-    let mut panels: HashMap<(i64, i64), bool> = HashMap::new();
-
-    input.send(start_white as i64).unwrap();
-
-    let output = iter::from_fn(move || {
-        output
-            .recv()
-            .ok()
-            .map(|white| {
-                let white = white == 1;
-                let right = output.recv().unwrap() == 1;
-                (white, right)
-            })
-    });
-
-    output
-        .take_while(|&(white, right)| {
-            panels.insert(position, white);
-
-            if right { direction.right() }
-            else { direction.left() };
-            direction.apply(&mut position);
-
-            let color = panels
-                .get(&position)
-                .map(|&n| n as i64)
-                .unwrap_or(0);
-            input.send(color).is_ok()
-        })
-        .for_each(|_| ());
-
-    panels
+) -> SparseGrid<bool> {
+    hull_robot(program, start_white)
+        .world
+        .painted()
+        .map(|(position, color)| (position, color == 1))
+        .collect()
 }
 
 #[aoc(day11, part1)]
 fn part1(input: &str) -> usize {
-    paint_panels(intcode::parse_program(input), false).len()
+    paint_panels(intcode::parse_program(input).unwrap(), false).len()
 }
 
 #[aoc(day11, part2)]
 fn part2(input: &str) -> Part2 {
-    let mut panels = paint_panels(intcode::parse_program(input), true);
-
-    let (x1, y1, dx, dy) = {
-        let &(mut x1, mut y1) = panels.keys().next().unwrap();
-        let (mut x2, mut y2) = (x1, y1);
-        panels.retain(|&(x, y), &mut white| {
-            if white {
-                if x < x1 { x1 = x; }
-                else if x > x2 { x2 = x; }
-                if y < y1 { y1 = y; }
-                else if y > y2 { y2 = y; }
-                true
-            } else { false }
-        });
-        (x1, y1, (x2 - x1 + 1) as usize, (y2 - y1 + 1) as usize)
-    };
-
-    let mut canvas = vec![' '; dx*dy];
-    panels
-        .into_iter()
-        .map(move |((x, y), _)| (((dx as i64) * (y - y1)) + (x - x1)) as usize)
-        .for_each(|i| canvas[i] = '█');
-
-    Part2(canvas, dx)
+    let mut panels = paint_panels(intcode::parse_program(input).unwrap(), true);
+    panels.retain(|_, &mut white| white);
+
+    let canvas = panels
+        .rows(|white| if white.is_some() { '█' } else { ' ' })
+        .concat();
+
+    Part2(canvas, panels.bounds().unwrap().width())
 }
 
 struct Part2(Vec<char>, usize);
 
 impl fmt::Display for Part2 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let grid: Vec<Vec<bool>> = self.0
+            .chunks(self.1)
+            .map(|line| line.iter().map(|&c| c == '█').collect())
+            .collect();
+        let text = ocr::recognise(&grid);
+        if let Ok(text) = &text {
+            return write!(f, "{}", text);
+        }
+
         write!(f, "\n┌─")?;
         for _ in 0..self.1 {
             write!(f, "─")?;
@@ -148,6 +117,10 @@ impl fmt::Display for Part2 {
         }
         write!(f, "─┘")?;
 
+        if let Err(err) = text {
+            write!(f, "\n{}", err)?;
+        }
+
         Ok(())
     }
 }
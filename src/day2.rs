@@ -2,15 +2,15 @@ use std::convert::TryInto;
 use std::iter;
 use aoc_runner_derive::aoc;
 
-fn execute(mut program: &mut [usize], noun: usize, verb: usize) -> usize {
+fn execute(program: &mut [usize], noun: usize, verb: usize) -> usize {
     program[1] = noun;
     program[2] = verb;
 
     for index in iter::successors(Some(0), |n| Some(n + 4)) {
         let op = program[index];
         match op {
-            1 => operate(&mut program, index, |a, b| a + b),
-            2 => operate(&mut program, index, |a, b| a * b),
+            1 => operate(program, index, |a, b| a + b),
+            2 => operate(program, index, |a, b| a * b),
             99 => break,
             _ => panic!("erroneous program!"),
         }
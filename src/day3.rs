@@ -1,92 +1,51 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
 use aoc_runner_derive::aoc;
 
-type Movement = (i64, i64, i64);
+use crate::wire::{Circuit, Metric};
 
-fn parse_distance(distance: &str) -> i64 {
-    distance.parse().unwrap()
-}
-
-fn parse_input<'a>(input: &'a str) -> [impl Iterator<Item = Movement> + 'a; 2] {
-    let mut iter = input
-        .lines()
-        .map(|line| {
-            line
-                .split(",")
-                .map(|movement| {
-                    match &movement[0..1] {
-                        "R" => (1, 0, parse_distance(&movement[1..])),
-                        "U" => (0, 1, parse_distance(&movement[1..])),
-                        "D" => (0, -1, parse_distance(&movement[1..])),
-                        "L" => (-1, 0, parse_distance(&movement[1..])),
-                        _ => panic!("invalid movement"),
-                    }
-                })
-        });
-    
-    [iter.next().unwrap(), iter.next().unwrap()]
-}
-
-macro_rules! enumerate_points {
-    ($wire:expr, $pos_ref:expr) => {{
-        ($wire)
-            .flat_map(|(dx, dy, distance)| {
-                (0..distance)
-                    .map(move |_| {
-                        let mut pos = ($pos_ref).borrow_mut();
-                        pos.0 += dx;
-                        pos.1 += dy;
-                        *pos
-                    })
-            })
-    }};
+pub fn parse_input(input: &str) -> Circuit {
+    let circuit = Circuit::parse(input).unwrap();
+    assert!(circuit.wires().len() >= 2, "need at least two wires");
+    circuit
 }
 
 #[aoc(day3, part1)]
 fn part1(input: &str) -> i64 {
-    let [first, second] = parse_input(input);
-
-    let pos = RefCell::new((0, 0));
-    let pos_ref = &pos;
-
-    let wiring: HashSet<(i64, i64)> = enumerate_points!(first, pos_ref)
-        .collect();
-
-    pos.replace((0, 0));
-
-    enumerate_points!(second, pos_ref)
-        .filter_map(|pos| {
-            if wiring.contains(&pos) {
-                Some(pos.0.abs() + pos.1.abs())
-            } else {
-                None
-            }
-        })
-        .min().unwrap()
+    parse_input(input)
+        .closest(Metric::Manhattan)
+        .unwrap()
+        .crossing
+        .manhattan()
 }
 
 #[aoc(day3, part2)]
 fn part2(input: &str) -> i64 {
-    let [first, second] = parse_input(input);
-
-    let pos = RefCell::new((0, 0));
-    let pos_ref = &pos;
-
-    let wiring: HashMap<(i64, i64), i64> = enumerate_points!(first, pos_ref)
-        .zip(1..)
-        .collect();
-
-    pos.replace((0, 0));
+    parse_input(input)
+        .closest(Metric::Delay)
+        .unwrap()
+        .crossing
+        .delay()
+}
 
-    enumerate_points!(second, pos_ref)
-        .zip(1..)
-        .filter_map(|(pos, distance2)| {
-            if let Some(&distance1) = wiring.get(&pos) {
-                Some(distance1 + distance2)
-            } else {
-                None
-            }
-        })
-        .min().unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLES: [&str; 2] = [
+        "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83",
+        "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7",
+    ];
+
+    #[test]
+    fn examples() {
+        assert_eq!(part1(EXAMPLES[0]), 159);
+        assert_eq!(part1(EXAMPLES[1]), 135);
+        assert_eq!(part2(EXAMPLES[0]), 610);
+        assert_eq!(part2(EXAMPLES[1]), 410);
+    }
+
+    #[test]
+    fn delay_counts_first_visit() {
+        // The first wire passes (2, 0) on its 2nd step and again on its 10th.
+        assert_eq!(part2("R4,U2,L2,D2\nD1,R2,U1"), 6);
+    }
 }
@@ -1,65 +1,384 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
 use std::iter;
-use std::ops::RangeInclusive;
+use std::ops::{RangeBounds, RangeInclusive};
 use aoc_runner_derive::aoc;
 
-fn parse_input(input: &str) -> RangeInclusive<u32> {
+fn parse_input(input: &str) -> RangeInclusive<u64> {
     let mut iter = input
+        .trim()
         .split("-")
-        .map(|n| n.parse::<u32>().unwrap());
-    
+        .map(|n| n.parse::<u64>().unwrap());
+
     iter.next().unwrap() ..= iter.next().unwrap()
 }
 
-fn get_digits(n: u32) -> [u8; 6] {
-    let mut iter = iter::successors(
-            Some(100000),
-            |&n| Some(n / 10),
-        )
-        .map(|d| (n / d % 10) as u8);
-    
-    [
-        iter.next().unwrap(),
-        iter.next().unwrap(),
-        iter.next().unwrap(),
-        iter.next().unwrap(),
-        iter.next().unwrap(),
-        iter.next().unwrap(),
-    ]
+/// The digits of `n`, most significant first, padded with zeroes to `width`.
+fn get_digits(n: u64, width: usize) -> Vec<u8> {
+    let mut digits: Vec<u8> = (0..width)
+        .scan(n, |n, _| {
+            let digit = (*n % 10) as u8;
+            *n /= 10;
+            Some(digit)
+        })
+        .collect();
+    digits.reverse();
+    digits
+}
+
+// Counts the strings of digits no greater than `bound` that never decrease
+// and have a run of equal digits whose length is in `runs`. Once a string
+// drops below the bound, what's left of it can be anything, so those counts
+// only depend on the position, the last digit, the length of the run it ends
+// and whether a run has been found yet.
+struct Counter<'a, R> {
+    bound: &'a [u8],
+    runs: &'a R,
+    memo: HashMap<(usize, u8, usize, bool), u64>,
+}
+
+impl<R: RangeBounds<usize>> Counter<'_, R> {
+    fn count(&mut self, pos: usize, last: u8, run: usize, found: bool, tight: bool) -> u64 {
+        if pos == self.bound.len() {
+            return (found || self.runs.contains(&run)) as u64;
+        }
+
+        let key = (pos, last, run, found);
+        if !tight {
+            if let Some(&count) = self.memo.get(&key) {
+                return count;
+            }
+        }
+
+        let limit = if tight { self.bound[pos] } else { 9 };
+        let count = (last ..= limit)
+            .map(|digit| {
+                let (run, found) = if digit == last && run > 0 {
+                    (run + 1, found)
+                } else {
+                    (1, found || (run > 0 && self.runs.contains(&run)))
+                };
+                self.count(pos + 1, digit, run, found, tight && digit == limit)
+            })
+            .sum();
+
+        if !tight {
+            self.memo.insert(key, count);
+        }
+        count
+    }
+}
+
+/// Counts the numbers in `range` whose digits never decrease and that have a
+/// run of equal digits with a length in `runs`. Digits are read without
+/// leading zeroes, as `Rules` reads them.
+pub fn count_passwords<R>(range: RangeInclusive<u64>, runs: R) -> u64
+where
+    R: RangeBounds<usize>,
+{
+    let count_to = |n: u64, width: usize| {
+        let bound = get_digits(n, width);
+        let mut counter = Counter { bound: &bound, runs: &runs, memo: HashMap::new() };
+        counter.count(0, 0, 0, false, true)
+    };
+
+    // Padding with zeroes would make them part of a run, so each width is
+    // counted on its own. Counting up to just below the first number with
+    // `width` digits takes off every string with leading zeroes.
+    (1..=20)
+        .map(|width| {
+            let first = if width == 1 { 0 } else { 10u64.pow(width as u32 - 1) };
+            let last = 10u64.checked_pow(width as u32).map_or(u64::MAX, |n| n - 1);
+            let (lo, hi) = ((*range.start()).max(first), (*range.end()).min(last));
+            if lo > hi {
+                return 0;
+            }
+            let below = if lo == 0 { 0 } else { count_to(lo - 1, width) };
+            count_to(hi, width) - below
+        })
+        .sum()
+}
+
+/// A single password criterion. Digits are read without leading zeroes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rule {
+    NonDecreasing,
+    /// Some run of equal digits is between `min` and `max` long.
+    HasRun { min: usize, max: usize },
+    DigitCount(usize),
+    InRange(u64, u64),
+}
+
+pub fn non_decreasing() -> Rule {
+    Rule::NonDecreasing
+}
+
+/// Use a `max` of `usize::MAX` to allow runs of any length.
+pub fn has_run(min: usize, max: usize) -> Rule {
+    Rule::HasRun { min, max }
+}
+
+pub fn digit_count(n: usize) -> Rule {
+    Rule::DigitCount(n)
+}
+
+pub fn in_range(a: u64, b: u64) -> Rule {
+    Rule::InRange(a, b)
+}
+
+fn runs(digits: &[u8]) -> Vec<usize> {
+    let mut runs: Vec<usize> = Vec::new();
+    for (i, digit) in digits.iter().enumerate() {
+        if i > 0 && digits[i - 1] == *digit {
+            *runs.last_mut().unwrap() += 1;
+        } else {
+            runs.push(1);
+        }
+    }
+    runs
+}
+
+impl Rule {
+    /// Why `n` breaks the rule, if it does.
+    pub fn explain(&self, n: u64) -> Option<String> {
+        let digits: Vec<u8> = n.to_string().bytes().map(|b| b - b'0').collect();
+        match *self {
+            Rule::NonDecreasing => digits
+                .windows(2)
+                .position(|window| window[0] > window[1])
+                .map(|i| {
+                    format!("{} drops to {} at position {}", digits[i], digits[i + 1], i + 1)
+                }),
+            Rule::HasRun { min, max } => {
+                let runs = runs(&digits);
+                if runs.iter().any(|run| (min ..= max).contains(run)) {
+                    None
+                } else {
+                    Some(format!("its runs are {:?} long", runs))
+                }
+            },
+            Rule::DigitCount(count) if digits.len() != count =>
+                Some(format!("it has {} digits", digits.len())),
+            Rule::InRange(a, b) if n < a || n > b =>
+                Some(format!("it is outside {}-{}", a, b)),
+            Rule::DigitCount(_) | Rule::InRange(..) => None,
+        }
+    }
+
+    pub fn accepts(&self, n: u64) -> bool {
+        self.explain(n).is_none()
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rule::NonDecreasing => write!(f, "non-decreasing digits"),
+            Rule::HasRun { min, max } if min == max =>
+                write!(f, "a run of exactly {} equal digits", min),
+            Rule::HasRun { min, max: usize::MAX } =>
+                write!(f, "a run of at least {} equal digits", min),
+            Rule::HasRun { min, max } =>
+                write!(f, "a run of {} to {} equal digits", min, max),
+            Rule::DigitCount(count) => write!(f, "{} digits", count),
+            Rule::InRange(a, b) => write!(f, "a value from {} to {}", a, b),
+        }
+    }
+}
+
+/// The first rule a candidate broke, and how.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub rule: Rule,
+    pub reason: String,
 }
 
-fn is_ordered(digits: [u8; 6]) -> bool {
-    digits.windows(2)
-        .filter(|window| window[1] < window[0])
-        .next().is_none()
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "needs {}, but {}", self.rule, self.reason)
+    }
+}
+
+// The smallest number at least `n` whose digits never decrease: everything
+// after the first drop is raised to the digit before it.
+fn next_non_decreasing(n: u64) -> Option<u64> {
+    let mut digits: Vec<u8> = n.to_string().bytes().map(|b| b - b'0').collect();
+    if let Some(i) = digits.windows(2).position(|window| window[0] > window[1]) {
+        let digit = digits[i];
+        digits[i + 1..].iter_mut().for_each(|d| *d = digit);
+    }
+    let n = digits.iter().fold(0u128, |n, &digit| n * 10 + digit as u128);
+    u64::try_from(n).ok()
+}
+
+/// Criteria that a password must meet all of.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn new() -> Self {
+        Rules::default()
+    }
+
+    pub fn and(mut self, rule: Rule) -> Self {
+        self.0.push(rule);
+        self
+    }
+
+    pub fn check(&self, n: u64) -> Result<(), Rejection> {
+        for rule in &self.0 {
+            if let Some(reason) = rule.explain(n) {
+                return Err(Rejection { rule: *rule, reason });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn accepts(&self, n: u64) -> bool {
+        self.check(n).is_ok()
+    }
+
+    // The narrowest range the range and digit count rules allow.
+    fn bounds(&self) -> (u64, u64) {
+        self.0.iter().fold((0, u64::MAX), |(lo, hi), rule| match *rule {
+            Rule::InRange(a, b) => (lo.max(a), hi.min(b)),
+            Rule::DigitCount(0) => (1, 0),
+            Rule::DigitCount(count) => {
+                let first = 10u64.checked_pow(count as u32 - 1).unwrap_or(u64::MAX);
+                let last = 10u64.checked_pow(count as u32).map_or(u64::MAX, |n| n - 1);
+                (lo.max(if count == 1 { 0 } else { first }), hi.min(last))
+            },
+            _ => (lo, hi),
+        })
+    }
+
+    /// Every matching number in increasing order. Without range or digit
+    /// count rules this goes on up to `u64::MAX`. With `non_decreasing()`
+    /// numbers that break it are skipped without being checked.
+    pub fn matches(&self) -> impl Iterator<Item = u64> + '_ {
+        let (lo, hi) = self.bounds();
+        let ordered = self.0.contains(&Rule::NonDecreasing);
+        let next = move |n: u64| if ordered { next_non_decreasing(n) } else { Some(n) };
+
+        iter::successors(next(lo), move |&n| n.checked_add(1).and_then(next))
+            .take_while(move |&n| n <= hi)
+            .filter(move |&n| self.accepts(n))
+    }
+
+    pub fn count(&self) -> usize {
+        self.matches().count()
+    }
+}
+
+fn password_rules(input: &str) -> Rules {
+    let range = parse_input(input);
+    Rules::new()
+        .and(digit_count(6))
+        .and(in_range(*range.start(), *range.end()))
+        .and(non_decreasing())
 }
 
 #[aoc(day4, part1)]
 fn part1(input: &str) -> usize {
-    parse_input(input)
-        .filter(|&n| {
-            let digits = get_digits(n);
-            is_ordered(digits)
-                && digits.windows(2)
-                    .filter(|window| window[0] == window[1])
-                    .next()
-                    .is_some()
-        })
+    password_rules(input)
+        .and(has_run(2, usize::MAX))
         .count()
 }
 
 #[aoc(day4, part2)]
 fn part2(input: &str) -> usize {
-    parse_input(input)
-        .filter(|&n| {
-            let digits = get_digits(n);
-            is_ordered(digits)
-                && {
-                    let mut counts = [0u8; 10];
-                    for &digit in digits.iter() {
-                        counts[digit as usize] += 1;
-                    }
-                    counts.contains(&2)
-                }
-        })
+    password_rules(input)
+        .and(has_run(2, 2))
         .count()
 }
+
+// The puzzle's range, cut down to six digit numbers.
+fn six_digits(input: &str) -> RangeInclusive<u64> {
+    let range = parse_input(input);
+    (*range.start()).max(100_000) ..= (*range.end()).min(999_999)
+}
+
+#[aoc(day4, part1, Dp)]
+fn part1_dp(input: &str) -> u64 {
+    count_passwords(six_digits(input), 2..)
+}
+
+#[aoc(day4, part2, Dp)]
+fn part2_dp(input: &str) -> u64 {
+    count_passwords(six_digits(input), 2..=2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(lo: u64, hi: u64, min: usize, max: usize) -> Rules {
+        Rules::new()
+            .and(in_range(lo, hi))
+            .and(non_decreasing())
+            .and(has_run(min, max))
+    }
+
+    fn dp(lo: u64, hi: u64, min: usize, max: usize) -> u64 {
+        count_passwords(lo..=hi, min..=max)
+    }
+
+    #[test]
+    fn leading_zeroes_are_not_runs() {
+        let any = usize::MAX;
+        assert_eq!(dp(0, 99, 2, any), 9);
+        assert_eq!(dp(123, 4567, 2, any), 356);
+        assert_eq!(dp(0, 999_999, 2, any), 4539);
+        assert_eq!(dp(0, 9, 1, 1), 10);
+        assert_eq!(dp(0, 0, 2, any), 0);
+        // No run is empty.
+        assert_eq!(dp(100, 999, 0, 0), 0);
+        assert_eq!(dp(100, 999, 0, 1), 156);
+    }
+
+    #[test]
+    fn parts_agree() {
+        for input in &["134792-675810", "0-99999", "99999-1000010"] {
+            assert_eq!(part1(input) as u64, part1_dp(input));
+            assert_eq!(part2(input) as u64, part2_dp(input));
+        }
+    }
+
+    #[test]
+    fn dp_agrees_with_rules() {
+        // A small linear congruential generator keeps the ranges repeatable.
+        let mut seed = 0x2019u64;
+        let mut random = move |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % n
+        };
+
+        let runs = [(2, usize::MAX), (2, 2), (1, 1), (3, 4), (0, 0), (0, 1)];
+        for _ in 0..100 {
+            // Mostly short ranges, some of them crossing a power of ten.
+            let lo = match random(3) {
+                0 => random(1000),
+                1 => 10u64.pow(2 + random(5) as u32) - random(50),
+                _ => random(3_000_000),
+            };
+            let span = if random(4) == 0 { 1_000_000 } else { 500 };
+            let hi = lo + random(span);
+            for &(min, max) in &runs {
+                assert_eq!(
+                    dp(lo, hi, min, max),
+                    rules(lo, hi, min, max).count() as u64,
+                    "range {}-{} with runs {}-{}", lo, hi, min, max,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counts_huge_ranges() {
+        // Non-decreasing numbers below 10^18 are 0 and the multisets of
+        // 1 to 18 digits from 1-9, which add up to C(27, 9).
+        assert_eq!(dp(0, 10u64.pow(18) - 1, 1, usize::MAX), 4_686_825);
+        assert!(dp(0, u64::MAX, 2, 2) > 0);
+    }
+}
@@ -11,10 +11,10 @@ fn run_diagnostics(program: &mut Vec<i64>, id: i64) -> i64 {
 
 #[aoc(day5, part1)]
 fn part1(input: &str) -> i64 {
-    run_diagnostics(&mut intcode::parse_program(input), 1)
+    run_diagnostics(&mut intcode::parse_program(input).unwrap(), 1)
 }
 
 #[aoc(day5, part2)]
 fn part2(input: &str) -> i64 {
-    run_diagnostics(&mut intcode::parse_program(input), 5)
+    run_diagnostics(&mut intcode::parse_program(input).unwrap(), 5)
 }
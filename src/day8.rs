@@ -1,30 +1,25 @@
 use std::fmt;
-use std::str;
 use aoc_runner_derive::aoc;
 
-fn parse_input(input: &str) -> Vec<Vec<u8>> {
-    input
-        .as_bytes()
-        .chunks(25*6)
-        .map(|bytes| bytes.into())
-        .collect()
+use crate::ocr;
+use crate::sif::{self, SpaceImage};
+
+pub fn parse_input(input: &str) -> SpaceImage {
+    SpaceImage::decode(input, 25, 6).unwrap()
 }
 
 #[aoc(day8, part1)]
 fn part1(input: &str) -> usize {
-    let layers = parse_input(input);
+    let image = parse_input(input);
 
-    layers
+    image
+        .layer_stats()
         .into_iter()
-        .map(|layer| {
-            let count = move |n| {
-                layer
-                    .iter()
-                    .filter(|&&c| c == n)
-                    .count()
-            };
-
-            (count(b'0'), count(b'1') * count(b'2'))
+        .map(|stats| {
+            (
+                stats.count(sif::BLACK),
+                stats.count(sif::WHITE) * stats.count(sif::TRANSPARENT),
+            )
         })
         .min_by_key(|&(zeroes, _)| zeroes)
         .unwrap()
@@ -33,38 +28,39 @@ fn part1(input: &str) -> usize {
 
 #[aoc(day8, part2)]
 fn part2(input: &str) -> Part2 {
-    let layers = parse_input(input);
+    let image = parse_input(input).composite();
 
-    layers
-        .into_iter()
-        .rev()
-        .flat_map(|layer| {
-            layer
-                .into_iter()
-                .enumerate()
-        })
-        .fold(
-            Part2(vec![' '; 25*6]),
-            |Part2(mut image), (i, color)| {
-                match color {
-                    b'0' => image[i] = ' ',
-                    b'1' => image[i] = '█',
-                    _ => (),
-                }
-                Part2(image)
-            }
-        )
+    Part2(
+        image.pixels
+            .iter()
+            .map(|&color| if color == sif::WHITE { '█' } else { ' ' })
+            .collect(),
+        image.width,
+    )
 }
 
-struct Part2(Vec<char>);
+struct Part2(Vec<char>, usize);
 
 impl fmt::Display for Part2 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "\n┌───────────────────────────┐")?;
-        for line in self.0.chunks(25) {
+        let grid: Vec<Vec<bool>> = self.0
+            .chunks(self.1)
+            .map(|line| line.iter().map(|&c| c == '█').collect())
+            .collect();
+        let text = ocr::recognise(&grid);
+        if let Ok(text) = &text {
+            return write!(f, "{}", text);
+        }
+
+        write!(f, "\n┌─{}─┐", "─".repeat(self.1))?;
+        for line in self.0.chunks(self.1) {
             write!(f, "\n│ {} │", line.iter().collect::<String>())?;
         }
-        write!(f, "\n└───────────────────────────┘")?;
+        write!(f, "\n└─{}─┘", "─".repeat(self.1))?;
+
+        if let Err(err) = text {
+            write!(f, "\n{}", err)?;
+        }
 
         Ok(())
     }
@@ -18,10 +18,10 @@ fn run_boost(mut program: Vec<i64>, input: i64) -> i64 {
 
 #[aoc(day9, part1)]
 fn part1(input: &str) -> i64 {
-    run_boost(intcode::parse_program(input), 1)
+    run_boost(intcode::parse_program(input).unwrap(), 1)
 }
 
 #[aoc(day9, part2)]
 fn part2(input: &str) -> i64 {
-    run_boost(intcode::parse_program(input), 2)
+    run_boost(intcode::parse_program(input).unwrap(), 2)
 }
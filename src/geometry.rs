@@ -0,0 +1,229 @@
+//! Points and directions on the puzzles' integer plane.
+//!
+//! Everything here uses screen orientation: `x` increases to the right and
+//! `y` increases *downwards*, the same as rows in a character map. So `Up` is
+//! `(0, -1)`, and a quarter turn to the right takes `Up` to `Right`. Puzzles
+//! that describe movement with `U`/`D` letters only care about distances, so
+//! their answers don't depend on the choice.
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    pub const fn new(x: i64, y: i64) -> Self {
+        Point { x, y }
+    }
+
+    /// The Manhattan distance from the origin.
+    pub fn manhattan(self) -> i64 {
+        self.x.abs() + self.y.abs()
+    }
+
+    pub fn distance(self, other: Point) -> i64 {
+        (self - other).manhattan()
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl From<(i64, i64)> for Point {
+    fn from((x, y): (i64, i64)) -> Self {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for (i64, i64) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        Point::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        Point::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<i64> for Point {
+    type Output = Point;
+
+    fn mul(self, n: i64) -> Point {
+        Point::new(self.x * n, self.y * n)
+    }
+}
+
+impl Neg for Point {
+    type Output = Point;
+
+    fn neg(self) -> Point {
+        Point::new(-self.x, -self.y)
+    }
+}
+
+impl AddAssign for Point {
+    fn add_assign(&mut self, other: Point) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Point {
+    fn sub_assign(&mut self, other: Point) {
+        *self = *self - other;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Left,
+    Right,
+    Around,
+}
+
+impl Turn {
+    /// Reads the turn codes used by Intcode robots: 0 is left, 1 is right.
+    pub fn from_code(code: i64) -> Option<Turn> {
+        match code {
+            0 => Some(Turn::Left),
+            1 => Some(Turn::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Right,
+    Up,
+    Left,
+    Down,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Right, Direction::Up, Direction::Left, Direction::Down,
+    ];
+
+    /// Reads `R`, `U`, `L` or `D`.
+    pub fn from_letter(letter: char) -> Option<Direction> {
+        match letter {
+            'R' => Some(Direction::Right),
+            'U' => Some(Direction::Up),
+            'L' => Some(Direction::Left),
+            'D' => Some(Direction::Down),
+            _ => None,
+        }
+    }
+
+    pub fn delta(self) -> Point {
+        match self {
+            Direction::Right => Point::new(1, 0),
+            Direction::Up => Point::new(0, -1),
+            Direction::Left => Point::new(-1, 0),
+            Direction::Down => Point::new(0, 1),
+        }
+    }
+
+    pub fn turn(self, turn: Turn) -> Direction {
+        let quarters = match turn {
+            Turn::Left => 1,
+            Turn::Around => 2,
+            Turn::Right => 3,
+        };
+        Direction::ALL[(self as usize + quarters) % 4]
+    }
+}
+
+impl Add<Direction> for Point {
+    type Output = Point;
+
+    fn add(self, direction: Direction) -> Point {
+        self + direction.delta()
+    }
+}
+
+impl AddAssign<Direction> for Point {
+    fn add_assign(&mut self, direction: Direction) {
+        *self = *self + direction;
+    }
+}
+
+/// The eight compass directions, for when diagonal moves are allowed. Turns
+/// rotate by an eighth, except `Around`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction8 {
+    East,
+    NorthEast,
+    North,
+    NorthWest,
+    West,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+impl Direction8 {
+    pub const ALL: [Direction8; 8] = [
+        Direction8::East, Direction8::NorthEast,
+        Direction8::North, Direction8::NorthWest,
+        Direction8::West, Direction8::SouthWest,
+        Direction8::South, Direction8::SouthEast,
+    ];
+
+    pub fn delta(self) -> Point {
+        match self {
+            Direction8::East => Point::new(1, 0),
+            Direction8::NorthEast => Point::new(1, -1),
+            Direction8::North => Point::new(0, -1),
+            Direction8::NorthWest => Point::new(-1, -1),
+            Direction8::West => Point::new(-1, 0),
+            Direction8::SouthWest => Point::new(-1, 1),
+            Direction8::South => Point::new(0, 1),
+            Direction8::SouthEast => Point::new(1, 1),
+        }
+    }
+
+    pub fn turn(self, turn: Turn) -> Direction8 {
+        let eighths = match turn {
+            Turn::Left => 1,
+            Turn::Around => 4,
+            Turn::Right => 7,
+        };
+        Direction8::ALL[(self as usize + eighths) % 8]
+    }
+}
+
+impl From<Direction> for Direction8 {
+    fn from(direction: Direction) -> Self {
+        Direction8::ALL[direction as usize * 2]
+    }
+}
+
+impl Add<Direction8> for Point {
+    type Output = Point;
+
+    fn add(self, direction: Direction8) -> Point {
+        self + direction.delta()
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::iter::FromIterator;
+
+use crate::geometry::{Direction, Direction8, Point};
+
+/// An inclusive rectangle of positions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub x1: i64,
+    pub y1: i64,
+    pub x2: i64,
+    pub y2: i64,
+}
+
+impl Bounds {
+    pub fn point(Point { x, y }: Point) -> Self {
+        Bounds { x1: x, y1: y, x2: x, y2: y }
+    }
+
+    pub fn of<I: IntoIterator<Item = Point>>(positions: I) -> Option<Self> {
+        let mut positions = positions.into_iter();
+        let first = Bounds::point(positions.next()?);
+        Some(positions.fold(first, Bounds::extend))
+    }
+
+    pub fn extend(self, Point { x, y }: Point) -> Self {
+        Bounds {
+            x1: self.x1.min(x),
+            y1: self.y1.min(y),
+            x2: self.x2.max(x),
+            y2: self.y2.max(y),
+        }
+    }
+
+    pub fn contains(&self, Point { x, y }: Point) -> bool {
+        (self.x1 ..= self.x2).contains(&x) && (self.y1 ..= self.y2).contains(&y)
+    }
+
+    pub fn width(&self) -> usize {
+        (self.x2 - self.x1 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.y2 - self.y1 + 1) as usize
+    }
+
+    /// Every position in the rectangle, row by row.
+    pub fn positions(self) -> impl Iterator<Item = Point> {
+        (self.y1 ..= self.y2)
+            .flat_map(move |y| {
+                (self.x1 ..= self.x2).map(move |x| Point::new(x, y))
+            })
+    }
+}
+
+pub fn neighbours4(pos: Point) -> impl Iterator<Item = Point> {
+    Direction::ALL.iter().map(move |&direction| pos + direction)
+}
+
+pub fn neighbours8(pos: Point) -> impl Iterator<Item = Point> {
+    Direction8::ALL.iter().map(move |&direction| pos + direction)
+}
+
+/// What the dense and sparse grids have in common. Positions follow the
+/// orientation described in `geometry`, with `y` increasing downwards.
+pub trait Grid<T> {
+    fn get(&self, pos: Point) -> Option<&T>;
+
+    /// The smallest rectangle containing every cell with a value.
+    fn bounds(&self) -> Option<Bounds>;
+
+    fn contains(&self, pos: Point) -> bool {
+        self.get(pos).is_some()
+    }
+
+    /// The orthogonal neighbours of `pos` that have a value.
+    fn neighbours(&self, pos: Point) -> Vec<(Point, &T)> {
+        neighbours4(pos)
+            .filter_map(|pos| self.get(pos).map(|value| (pos, value)))
+            .collect()
+    }
+
+    /// The orthogonal and diagonal neighbours of `pos` that have a value.
+    fn neighbours_diagonal(&self, pos: Point) -> Vec<(Point, &T)> {
+        neighbours8(pos)
+            .filter_map(|pos| self.get(pos).map(|value| (pos, value)))
+            .collect()
+    }
+
+    /// Maps every position within the bounds, row by row.
+    fn rows<U, F>(&self, mut f: F) -> Vec<Vec<U>>
+    where
+        F: FnMut(Option<&T>) -> U,
+    {
+        match self.bounds() {
+            Some(bounds) => (bounds.y1 ..= bounds.y2)
+                .map(|y| {
+                    (bounds.x1 ..= bounds.x2)
+                        .map(|x| f(self.get(Point::new(x, y))))
+                        .collect()
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn render<F>(&self, glyph: F) -> String
+    where
+        F: FnMut(Option<&T>) -> char,
+    {
+        self.rows(glyph)
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A grid storing only the cells that have been set, for unbounded or mostly
+/// empty areas.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    cells: HashMap<Point, T>,
+    bounds: Option<Bounds>,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid {
+            cells: HashMap::new(),
+            bounds: None,
+        }
+    }
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> Self {
+        SparseGrid::default()
+    }
+
+    /// Builds a grid from a character map, keeping the cells for which
+    /// `cell` gives a value.
+    pub fn parse<F>(input: &str, mut cell: F) -> Self
+    where
+        F: FnMut(char) -> Option<T>,
+    {
+        input
+            .lines()
+            .zip(0..)
+            .flat_map(|(line, y)| {
+                line.chars().zip(0..).map(move |(c, x)| (Point::new(x, y), c))
+            })
+            .filter_map(|(pos, c)| cell(c).map(|value| (pos, value)))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn insert(&mut self, pos: Point, value: T) -> Option<T> {
+        self.bounds = Some(match self.bounds {
+            Some(bounds) => bounds.extend(pos),
+            None => Bounds::point(pos),
+        });
+        self.cells.insert(pos, value)
+    }
+
+    pub fn get_mut(&mut self, pos: Point) -> Option<&mut T> {
+        self.cells.get_mut(&pos)
+    }
+
+    pub fn get_or_insert_with<F>(&mut self, pos: Point, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        if !self.cells.contains_key(&pos) {
+            self.insert(pos, f());
+        }
+        self.cells.get_mut(&pos).unwrap()
+    }
+
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Point, &mut T) -> bool,
+    {
+        self.cells.retain(|&pos, value| f(pos, value));
+        self.bounds = Bounds::of(self.cells.keys().copied());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.cells.iter().map(|(&pos, value)| (pos, value))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Point> + '_ {
+        self.cells.keys().copied()
+    }
+}
+
+impl<T> Grid<T> for SparseGrid<T> {
+    fn get(&self, pos: Point) -> Option<&T> {
+        self.cells.get(&pos)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+}
+
+impl<T> FromIterator<(Point, T)> for SparseGrid<T> {
+    fn from_iter<I: IntoIterator<Item = (Point, T)>>(iter: I) -> Self {
+        let mut grid = SparseGrid::new();
+        grid.extend(iter);
+        grid
+    }
+}
+
+impl<T> Extend<(Point, T)> for SparseGrid<T> {
+    fn extend<I: IntoIterator<Item = (Point, T)>>(&mut self, iter: I) {
+        for (pos, value) in iter {
+            self.insert(pos, value);
+        }
+    }
+}
+
+impl<T> IntoIterator for SparseGrid<T> {
+    type Item = (Point, T);
+    type IntoIter = hash_map::IntoIter<Point, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cells.into_iter()
+    }
+}
+
+/// A grid with a value at every position of a fixed rectangle.
+#[derive(Clone, Debug)]
+pub struct DenseGrid<T> {
+    cells: Vec<T>,
+    bounds: Bounds,
+}
+
+impl<T: Clone> DenseGrid<T> {
+    pub fn new(bounds: Bounds, value: T) -> Self {
+        DenseGrid {
+            cells: vec![value; bounds.width() * bounds.height()],
+            bounds,
+        }
+    }
+}
+
+impl<T> DenseGrid<T> {
+    /// Builds a grid from a character map with its top left corner at
+    /// `(0, 0)`. Short lines are padded with `cell(' ')`.
+    pub fn parse<F>(input: &str, mut cell: F) -> Self
+    where
+        F: FnMut(char) -> T,
+    {
+        let lines: Vec<&str> = input.lines().collect();
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let bounds = Bounds {
+            x1: 0,
+            y1: 0,
+            x2: width as i64 - 1,
+            y2: lines.len() as i64 - 1,
+        };
+        let cells = lines
+            .iter()
+            .flat_map(|line| {
+                line.chars().chain(std::iter::repeat(' ')).take(width)
+            })
+            .map(&mut cell)
+            .collect();
+
+        DenseGrid { cells, bounds }
+    }
+
+    fn index(&self, pos: Point) -> Option<usize> {
+        if self.bounds.contains(pos) {
+            let x = (pos.x - self.bounds.x1) as usize;
+            let y = (pos.y - self.bounds.y1) as usize;
+            Some(y * self.bounds.width() + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, pos: Point) -> Option<&mut T> {
+        let index = self.index(pos)?;
+        Some(&mut self.cells[index])
+    }
+
+    /// Sets the value at `pos`, panicking if it's outside the grid.
+    pub fn set(&mut self, pos: Point, value: T) {
+        match self.get_mut(pos) {
+            Some(cell) => *cell = value,
+            None => panic!("{:?} is outside {:?}", pos, self.bounds),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.bounds.positions().zip(&self.cells)
+    }
+}
+
+impl<T> Grid<T> for DenseGrid<T> {
+    fn get(&self, pos: Point) -> Option<&T> {
+        self.index(pos).map(|index| &self.cells[index])
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        if self.cells.is_empty() { None } else { Some(self.bounds) }
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::Write;
+
+pub type Rgba = [u8; 4];
+
+/// Colours for the indices stored in an `Image`. Indices without a colour are
+/// drawn transparent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette(pub Vec<Rgba>);
+
+impl Palette {
+    /// Black, white and transparent, as used by the Space Image Format and
+    /// the hull painting robot.
+    pub fn space_image() -> Self {
+        Palette(vec![[0, 0, 0, 255], [255, 255, 255, 255], [0, 0, 0, 0]])
+    }
+
+    /// The Space Image Format colours followed by colours for the digits 3
+    /// to 9 of extended images, some of them translucent.
+    pub fn extended() -> Self {
+        let mut palette = Palette::space_image();
+        palette.0.extend(&[
+            [230, 57, 70, 255],
+            [42, 157, 143, 255],
+            [38, 70, 200, 255],
+            [244, 162, 97, 255],
+            [255, 0, 0, 128],
+            [0, 255, 0, 128],
+            [0, 0, 255, 128],
+        ]);
+        palette
+    }
+
+    pub fn color(&self, index: u8) -> Rgba {
+        self.0.get(index as usize).copied().unwrap_or([0, 0, 0, 0])
+    }
+
+    pub fn set(&mut self, index: u8, color: Rgba) {
+        let index = index as usize;
+        if index >= self.0.len() {
+            self.0.resize(index + 1, [0, 0, 0, 0]);
+        }
+        self.0[index] = color;
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::space_image()
+    }
+}
+
+/// A picture of palette indices, stored row by row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Image { width, height, pixels }
+    }
+
+    pub fn from_rows(rows: Vec<Vec<u8>>) -> Self {
+        let width = rows.first().map_or(0, Vec::len);
+        let height = rows.len();
+        Image::new(width, height, rows.concat())
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    pub fn to_rgba(&self, palette: &Palette) -> RgbaImage {
+        RgbaImage {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|&index| palette.color(index)).collect(),
+        }
+    }
+}
+
+/// A picture of actual colours, for when layers have been blended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgba>,
+}
+
+impl RgbaImage {
+    pub fn rows(&self) -> impl Iterator<Item = &[Rgba]> {
+        self.pixels.chunks(self.width.max(1))
+    }
+}
+
+/// Blends `front` over `back` with straight (not premultiplied) alpha.
+pub fn blend(front: Rgba, back: Rgba) -> Rgba {
+    let (fa, ba) = (front[3] as f64 / 255.0, back[3] as f64 / 255.0);
+    let alpha = fa + ba * (1.0 - fa);
+    if alpha == 0.0 {
+        return [0, 0, 0, 0];
+    }
+
+    let mut out = [0; 4];
+    for i in 0..3 {
+        let color = (front[i] as f64 * fa + back[i] as f64 * ba * (1.0 - fa)) / alpha;
+        out[i] = color.round() as u8;
+    }
+    out[3] = (alpha * 255.0).round() as u8;
+    out
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    pub palette: Palette,
+    /// The side length, in output pixels, of each image pixel.
+    pub scale: usize,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            palette: Palette::default(),
+            scale: 10,
+        }
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 }
+        })
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+// Wraps the data in a zlib stream of uncompressed deflate blocks, which every
+// decoder understands and needs no compressor.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        out.push(blocks.peek().is_none() as u8);
+        out.extend(&len.to_le_bytes());
+        out.extend(&(!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(&adler32(data).to_be_bytes());
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(&crc.to_be_bytes());
+}
+
+/// Encodes the image as an 8-bit RGBA PNG.
+pub fn png(image: &Image, options: &ExportOptions) -> Vec<u8> {
+    png_rgba(&image.to_rgba(&options.palette), options.scale)
+}
+
+pub fn png_rgba(image: &RgbaImage, scale: usize) -> Vec<u8> {
+    let scale = scale.max(1);
+    let (width, height) = (image.width * scale, image.height * scale);
+
+    let mut raw = Vec::with_capacity((width * 4 + 1) * height);
+    for row in image.rows() {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&color| (0..scale).flat_map(move |_| color.to_vec()))
+            .collect();
+        for _ in 0..scale {
+            raw.push(0);
+            raw.extend(&line);
+        }
+    }
+
+    let mut header = Vec::new();
+    header.extend(&(width as u32).to_be_bytes());
+    header.extend(&(height as u32).to_be_bytes());
+    header.extend(&[8, 6, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Renders the image as SVG, with one rectangle for each horizontal run of
+/// a colour. Transparent pixels are left out.
+pub fn svg(image: &Image, options: &ExportOptions) -> String {
+    svg_rgba(&image.to_rgba(&options.palette), options.scale)
+}
+
+pub fn svg_rgba(image: &RgbaImage, scale: usize) -> String {
+    let scale = scale.max(1);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}" shape-rendering="crispEdges">"#,
+        image.width * scale,
+        image.height * scale,
+    );
+
+    for (y, row) in image.rows().enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            let run = row[x..]
+                .iter()
+                .take_while(|&&color| color == row[x])
+                .count();
+            let [r, g, b, a] = row[x];
+            if a > 0 {
+                let _ = write!(
+                    out,
+                    r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#{:02x}{:02x}{:02x}""##,
+                    x * scale, y * scale, run * scale, scale, r, g, b,
+                );
+                if a < 255 {
+                    let _ = write!(out, r#" fill-opacity="{:.3}""#, a as f64 / 255.0);
+                }
+                out.push_str("/>\n");
+            }
+            x += run;
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Renders the image for a terminal with 24-bit colour, two characters per
+/// pixel. Translucent pixels are blended over black and transparent ones use
+/// the terminal's own background.
+pub fn ansi(image: &RgbaImage) -> String {
+    let mut out = String::new();
+    for row in image.rows() {
+        for &color in row {
+            if color[3] == 0 {
+                out.push_str("\x1b[49m  ");
+            } else {
+                let [r, g, b, _] = blend(color, [0, 0, 0, 255]);
+                let _ = write!(out, "\x1b[48;2;{};{};{}m  ", r, g, b);
+            }
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
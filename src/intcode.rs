@@ -1,167 +1,375 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
 use std::iter;
+use std::mem;
+use std::ops::Range;
 
-#[derive(Clone, Copy)]
-enum IntOp {
-    Add, Mul, Lt, Eq,
+mod device;
+mod instruction;
+mod parse;
+mod scheduler;
+
+pub use device::{Device, Keyboard, Display, Random, Clock};
+pub use instruction::{
+    Context, Flow, Handler, Instruction, InstructionSet, Param, MAX_PARAMS,
+};
+pub use parse::{
+    ParseError, ParseErrorKind, parse_source, format_program, format_listing,
+    format_listing_with,
+};
+pub use scheduler::{ScheduleError, Scheduler};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownOpcode { opcode: i64, address: usize },
+    InputExhausted { address: usize },
+    NoDevice { port: i64, address: usize },
+    /// The instruction at `instruction` reached outside memory, or the
+    /// machine jumped there.
+    AddressOutOfRange { address: i64, instruction: usize },
 }
 
-pub fn execute<I, O>(
-    program: &mut Vec<i64>,
-    mut input: I,
-    mut output: O,
-)
-where
-    I: Iterator<Item = i64>,
-    O: FnMut(i64),
-{
-    let mut index = 0;
-    let mut base = 0;
-    loop {
-        let opcode = program[index] as usize;
-        index = match opcode % 100 {
-            1 => int_op(program, index, base, opcode, IntOp::Add),
-            2 => int_op(program, index, base, opcode, IntOp::Mul),
-            7 => int_op(program, index, base, opcode, IntOp::Lt),
-            8 => int_op(program, index, base, opcode, IntOp::Eq),
-            5 => jmp(program, index, base, opcode, true),
-            6 => jmp(program, index, base, opcode, false),
-            3 => inp(program, index, base, opcode, &mut input),
-            4 => out(program, index, base, opcode, &mut output),
-            9 => rel(program, index, opcode, &mut base),
-            99 => break,
-            _ => panic!("invalid opcode {}", opcode),
-        };
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { opcode, address } =>
+                write!(f, "invalid opcode {} at address {}", opcode, address),
+            Error::InputExhausted { address } =>
+                write!(f, "input exhausted at address {}", address),
+            Error::NoDevice { port, address } =>
+                write!(f, "no device attached to port {} at address {}", port, address),
+            Error::AddressOutOfRange { address, instruction } =>
+                write!(
+                    f,
+                    "address {} out of range for the instruction at address {}",
+                    address, instruction,
+                ),
+        }
     }
 }
 
-fn extract_params(
-    program: &mut Vec<i64>,
-    params: &mut [i64],
-    write_start: usize,
-    base: i64,
-    opcode: usize,
-) {
-    let mut modes = iter::successors(
-            Some(opcode / 100),
-            |n| Some(n / 10)
-        )
-        .map(|n| n % 10);
-    
-    params[..write_start]
-        .iter_mut()
-        .zip(&mut modes)
-        .for_each(|(param, mode)| match mode {
-            0 => *param = program[*param as usize],
-            2 => *param = program[(*param + base) as usize],
-            _ => (),
-        });
-    
-    params[write_start..]
-        .iter_mut()
-        .zip(modes)
-        .for_each(|(param, mode)| if mode == 2 {
-            *param += base;
-        })
+impl error::Error for Error {}
+
+/// Why `Machine::resume` stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Halted,
+    /// Waiting at an input instruction for a value that isn't there yet.
+    Blocked,
 }
 
-fn int_op(
-    program: &mut Vec<i64>,
+/// An Intcode computer with peripherals attached. Devices are reached either
+/// through a range of memory addresses, where reads and writes of parameters
+/// are forwarded to the device, or through numbered ports used by the
+/// `inport` (13) and `outport` (14) instructions of
+/// `InstructionSet::extended`. Machines use the standard set by default.
+#[derive(Default)]
+pub struct Machine {
+    memory: Vec<i64>,
     index: usize,
     base: i64,
-    opcode: usize,
-    op: IntOp,
-) -> usize {
-    let mut par = [program[index + 1], program[index + 2], program[index + 3]];
-    extract_params(program, &mut par, 2, base, opcode);
+    instructions: InstructionSet,
+    mapped: Vec<(Range<usize>, Box<dyn Device>)>,
+    ports: HashMap<i64, Box<dyn Device>>,
+    // Whether devices have been ticked for the instruction at `index`, which
+    // stays set when it fails so that retrying it doesn't tick them again.
+    ticked: bool,
+}
 
-    let out = &mut program[par[2] as usize];
+impl Machine {
+    pub fn new(memory: Vec<i64>) -> Self {
+        Machine {
+            memory,
+            ..Machine::default()
+        }
+    }
 
-    match op {
-        IntOp::Add => *out = par[0] + par[1],
-        IntOp::Mul => *out = par[0] * par[1],
-        IntOp::Lt => *out = (par[0] < par[1]) as i64,
-        IntOp::Eq => *out = (par[0] == par[1]) as i64,
+    pub fn with_instructions(
+        memory: Vec<i64>,
+        instructions: InstructionSet,
+    ) -> Self {
+        Machine {
+            memory,
+            instructions,
+            ..Machine::default()
+        }
     }
 
-    index + 4
-}
+    pub fn map_device<D>(&mut self, range: Range<usize>, device: D)
+    where
+        D: Device + 'static,
+    {
+        if let Some((mapped, _)) = self.mapped
+            .iter()
+            .find(|(mapped, _)| {
+                mapped.start < range.end && range.start < mapped.end
+            })
+        {
+            panic!("device at {:?} overlaps device at {:?}", range, mapped);
+        }
+        self.mapped.push((range, Box::new(device)));
+    }
 
-fn jmp(
-    program: &mut Vec<i64>,
-    index: usize,
-    base: i64,
-    opcode: usize,
-    mode: bool,
-) -> usize {
-    let mut par = [program[index + 1], program[index + 2]];
-    extract_params(program, &mut par, 2, base, opcode);
+    pub fn attach_port<D>(&mut self, port: i64, device: D)
+    where
+        D: Device + 'static,
+    {
+        self.ports.insert(port, Box::new(device));
+    }
 
-    if mode ^ (par[0] == 0) {
-        par[1] as usize
-    } else {
-        index + 3
+    pub fn memory(&self) -> &[i64] {
+        &self.memory
     }
-}
 
-fn out<O>(
-    program: &mut Vec<i64>,
-    index: usize,
-    base: i64,
-    opcode: usize,
-    mut output: O,
-) -> usize
-where
-    O: FnMut(i64),
-{
-    let mut par = [program[index + 1]];
-    extract_params(program, &mut par, 1, base, opcode);
+    pub fn into_memory(self) -> Vec<i64> {
+        self.memory
+    }
+
+    pub fn run<I, O>(&mut self, mut input: I, mut output: O) -> Result<(), Error>
+    where
+        I: Iterator<Item = i64>,
+        O: FnMut(i64),
+    {
+        loop {
+            if !self.ticked {
+                self.mapped
+                    .iter_mut()
+                    .for_each(|(_, device)| device.tick());
+                self.ports
+                    .values_mut()
+                    .for_each(|device| device.tick());
+                self.ticked = true;
+            }
+
+            let address = self.index;
+            let opcode = *self.memory
+                .get(address)
+                .ok_or(Error::AddressOutOfRange {
+                    address: address as i64,
+                    instruction: address,
+                })?;
+            let instruction = *self.instructions
+                .get(opcode)
+                .ok_or(Error::UnknownOpcode { opcode, address })?;
+
+            let len = instruction.params.len();
+            let mut par = [0; MAX_PARAMS];
+            par[..len].copy_from_slice(
+                self.memory
+                    .get(address + 1 ..= address + len)
+                    .ok_or(Error::AddressOutOfRange {
+                        address: self.memory.len() as i64,
+                        instruction: address,
+                    })?
+            );
+            self.extract_params(instruction.params, &mut par[..len], opcode)?;
 
-    output(par[0]);
+            let mut context = Context {
+                machine: self,
+                input: &mut input,
+                output: &mut output,
+            };
+            let flow = (instruction.handler)(&mut context, &par[..len])?;
+            self.ticked = false;
+            match flow {
+                Flow::Next => self.index += len + 1,
+                Flow::Jump(index) => self.index = index,
+                Flow::Halt => return Ok(()),
+            }
+        }
+    }
+
+    /// Runs until the machine halts or wants input that isn't queued. A
+    /// blocked machine carries on from the same input instruction when it's
+    /// resumed, and a halted one stays halted.
+    pub fn resume<O>(&mut self, input: &mut VecDeque<i64>, output: O) -> Result<Status, Error>
+    where
+        O: FnMut(i64),
+    {
+        match self.run(iter::from_fn(|| input.pop_front()), output) {
+            Ok(()) => Ok(Status::Halted),
+            Err(Error::InputExhausted { .. }) => Ok(Status::Blocked),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn out_of_range(&self, address: i64) -> Error {
+        Error::AddressOutOfRange { address, instruction: self.index }
+    }
+
+    fn load(&mut self, address: i64) -> Result<i64, Error> {
+        let index = usize::try_from(address)
+            .map_err(|_| self.out_of_range(address))?;
+        match self.mapped
+            .iter_mut()
+            .find(|(range, _)| range.contains(&index))
+        {
+            Some((range, device)) => Ok(device.read(index - range.start)),
+            None => match self.memory.get(index) {
+                Some(&value) => Ok(value),
+                None => Err(self.out_of_range(address)),
+            },
+        }
+    }
+
+    fn store(&mut self, address: i64, value: i64) -> Result<(), Error> {
+        let index = usize::try_from(address)
+            .map_err(|_| self.out_of_range(address))?;
+        match self.mapped
+            .iter_mut()
+            .find(|(range, _)| range.contains(&index))
+        {
+            Some((range, device)) => device.write(index - range.start, value),
+            None => match self.memory.get_mut(index) {
+                Some(cell) => *cell = value,
+                None => return Err(self.out_of_range(address)),
+            },
+        }
+        Ok(())
+    }
+
+    fn extract_params(
+        &mut self,
+        signature: &[Param],
+        params: &mut [i64],
+        opcode: i64,
+    ) -> Result<(), Error> {
+        let modes = iter::successors(
+                Some(opcode / 100),
+                |n| Some(n / 10)
+            )
+            .map(|n| n % 10);
 
-    index + 2
+        for ((param, kind), mode) in params.iter_mut().zip(signature).zip(modes) {
+            *param = match (kind, mode) {
+                (Param::Read, 0) => self.load(*param)?,
+                (Param::Read, 2) => self.load(*param + self.base)?,
+                (Param::Write, 2) => *param + self.base,
+                _ => *param,
+            };
+        }
+        Ok(())
+    }
 }
 
-fn inp<I>(
+pub fn execute<I, O>(
     program: &mut Vec<i64>,
-    index: usize,
-    base: i64,
-    opcode: usize,
-    mut input: I,
-) -> usize
+    input: I,
+    output: O,
+)
 where
     I: Iterator<Item = i64>,
+    O: FnMut(i64),
 {
-    let mut par = [program[index + 1]];
-    extract_params(program, &mut par, 0, base, opcode);
-
-    program[par[0] as usize] = input.next().unwrap();
+    let mut machine = Machine::new(mem::take(program));
+    if let Err(err) = machine.run(input, output) {
+        panic!("{}", err);
+    }
+    *program = machine.into_memory();
+}
 
-    index + 2
+pub fn parse_program(input: &str) -> Result<Vec<i64>, ParseError> {
+    let mut program = parse_source(input)?;
+    if program.len() < 10000 {
+        program.resize(10000, 0);
+    }
+    Ok(program)
 }
 
-fn rel(
-    program: &mut Vec<i64>,
-    index: usize,
-    opcode: usize,
-    base: &mut i64,
-) -> usize {
-    let mut par = [program[index + 1]];
-    extract_params(program, &mut par, 1, *base, opcode);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    *base += par[0];
+    fn run(program: Vec<i64>) -> Result<Vec<i64>, Error> {
+        let mut machine = Machine::new(program);
+        machine.run(iter::empty(), |_| ())?;
+        Ok(machine.into_memory())
+    }
 
-    index + 2
-}
+    #[test]
+    fn runs_programs() {
+        assert_eq!(run(vec![1, 0, 0, 0, 99]), Ok(vec![2, 0, 0, 0, 99]));
+        assert_eq!(run(vec![1002, 4, 3, 4, 33]), Ok(vec![1002, 4, 3, 4, 99]));
+    }
+
+    #[test]
+    fn addresses_out_of_range() {
+        assert_eq!(
+            run(vec![1, 100000, 0, 0, 99]),
+            Err(Error::AddressOutOfRange { address: 100000, instruction: 0 }),
+        );
+        assert_eq!(
+            run(vec![1, 0, 0, -1, 99]),
+            Err(Error::AddressOutOfRange { address: -1, instruction: 0 }),
+        );
+        assert_eq!(
+            run(vec![109, -5, 201, 0, 0, 0, 99]),
+            Err(Error::AddressOutOfRange { address: -5, instruction: 2 }),
+        );
+        assert_eq!(
+            run(vec![1105, 1, -3]),
+            Err(Error::AddressOutOfRange { address: -3, instruction: 0 }),
+        );
+        // Running off the end, and an instruction cut short by it.
+        assert_eq!(
+            run(vec![1, 0, 0, 0]),
+            Err(Error::AddressOutOfRange { address: 4, instruction: 4 }),
+        );
+        assert_eq!(
+            run(vec![1105, 1, 3, 1, 0]),
+            Err(Error::AddressOutOfRange { address: 5, instruction: 3 }),
+        );
+    }
+
+    #[test]
+    fn ports_need_the_extended_set() {
+        assert_eq!(
+            run(vec![13, 0, 0, 99]),
+            Err(Error::UnknownOpcode { opcode: 13, address: 0 }),
+        );
+        assert!(!format_listing(&[13, 0, 0, 99]).contains("inport"));
 
-pub fn parse_program(input: &str) -> Vec<i64> {
-    let mut program = vec![0; 10000];
+        let program = vec![113, 1, 5, 99, 0, 0];
+        let mut machine = Machine::with_instructions(program, InstructionSet::extended());
+        machine.attach_port(1, Keyboard::new("A"));
+        assert_eq!(machine.run(iter::empty(), |_| ()), Ok(()));
+        assert_eq!(machine.memory()[5], 'A' as i64);
+    }
+
+    #[test]
+    fn blocked_instructions_tick_once() {
+        let mut program = vec![1101, 0, 0, 20, 3, 20, 4, 100, 99];
+        program.resize(30, 0);
+        let mut machine = Machine::new(program);
+        machine.map_device(100..101, Clock::default());
 
-    input
-        .trim()
-        .split(",")
-        .map(|num| num.parse().unwrap())
-        .zip(program.iter_mut())
-        .for_each(|(num, target)| *target = num);
+        let mut input = VecDeque::new();
+        let mut outputs = Vec::new();
+        for _ in 0..3 {
+            let status = machine.resume(&mut input, |value| outputs.push(value));
+            assert_eq!(status, Ok(Status::Blocked));
+        }
+        input.push_back(7);
+        let status = machine.resume(&mut input, |value| outputs.push(value));
+        assert_eq!(status, Ok(Status::Halted));
+        // The add, the input and the output have run when the clock is read.
+        assert_eq!(outputs, vec![3]);
+    }
 
-    program
+    #[test]
+    fn scheduler_reports_bad_addresses() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(Machine::new(vec![3, 0, 99]));
+        scheduler.add(Machine::new(vec![4, -1, 99]));
+        scheduler.send(0, 1);
+        assert_eq!(
+            scheduler.run(),
+            Err(ScheduleError::Failed {
+                machine: 1,
+                error: Error::AddressOutOfRange { address: -1, instruction: 0 },
+            }),
+        );
+    }
 }
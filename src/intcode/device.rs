@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// A peripheral attached to a `Machine`. Offsets are relative to the start of
+/// the device's mapped range, and port devices are always accessed at offset
+/// zero. `tick` is called once before every instruction.
+pub trait Device {
+    fn read(&mut self, offset: usize) -> i64;
+    fn write(&mut self, offset: usize, value: i64);
+    fn tick(&mut self) {}
+}
+
+// Lets a caller keep a handle on a device after attaching it, e.g. to read
+// back a display once the program has halted.
+impl<D: Device> Device for Rc<RefCell<D>> {
+    fn read(&mut self, offset: usize) -> i64 {
+        self.borrow_mut().read(offset)
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        self.borrow_mut().write(offset, value)
+    }
+
+    fn tick(&mut self) {
+        self.borrow_mut().tick()
+    }
+}
+
+/// Offset 0 reads the next key, or -1 when none are pending, and writing to
+/// it queues a key. Offset 1 reads the number of pending keys.
+#[derive(Default)]
+pub struct Keyboard(VecDeque<i64>);
+
+impl Keyboard {
+    pub fn new(keys: &str) -> Self {
+        Keyboard(keys.chars().map(|c| c as i64).collect())
+    }
+}
+
+impl Device for Keyboard {
+    fn read(&mut self, offset: usize) -> i64 {
+        match offset {
+            0 => self.0.pop_front().unwrap_or(-1),
+            _ => self.0.len() as i64,
+        }
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        if offset == 0 {
+            self.0.push_back(value);
+        }
+    }
+}
+
+/// Writing to offset 0 prints a character and writing to offset 1 clears the
+/// screen. Reading any offset gives the number of characters shown.
+#[derive(Default)]
+pub struct Display(String);
+
+impl Display {
+    pub fn text(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Device for Display {
+    fn read(&mut self, _: usize) -> i64 {
+        self.0.chars().count() as i64
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        match offset {
+            0 => self.0.push(
+                std::char::from_u32(value as u32).unwrap_or('\u{fffd}')
+            ),
+            _ => self.0.clear(),
+        }
+    }
+}
+
+/// Reads give a non-negative pseudo-random number from a xorshift generator,
+/// and writes reseed it so runs can be reproduced.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: i64) -> Self {
+        let mut random = Random(0);
+        random.write(0, seed);
+        random
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new(2019)
+    }
+}
+
+impl Device for Random {
+    fn read(&mut self, _: usize) -> i64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 1) as i64
+    }
+
+    fn write(&mut self, _: usize, value: i64) {
+        // A zero state would make xorshift return zero forever.
+        self.0 = (value as u64) | 1;
+    }
+}
+
+/// Counts executed instructions. Reads give the count and writes set it.
+#[derive(Default)]
+pub struct Clock(i64);
+
+impl Device for Clock {
+    fn read(&mut self, _: usize) -> i64 {
+        self.0
+    }
+
+    fn write(&mut self, _: usize, value: i64) {
+        self.0 = value;
+    }
+
+    fn tick(&mut self) {
+        self.0 += 1;
+    }
+}
@@ -0,0 +1,207 @@
+use std::convert::TryFrom;
+
+use super::{Device, Error, Machine};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Param {
+    /// Resolved to a value according to the parameter's mode.
+    Read,
+    /// Resolved to the address to write to. Relative mode adds the base.
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    Jump(usize),
+    Halt,
+}
+
+pub const MAX_PARAMS: usize = 8;
+
+pub type Handler = fn(&mut Context, &[i64]) -> Result<Flow, Error>;
+
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub handler: Handler,
+}
+
+/// What an instruction handler has access to while it executes.
+pub struct Context<'a> {
+    pub(super) machine: &'a mut Machine,
+    pub(super) input: &'a mut dyn Iterator<Item = i64>,
+    pub(super) output: &'a mut dyn FnMut(i64),
+}
+
+impl Context<'_> {
+    pub fn address(&self) -> usize {
+        self.machine.index
+    }
+
+    pub fn load(&mut self, address: i64) -> Result<i64, Error> {
+        self.machine.load(address)
+    }
+
+    pub fn store(&mut self, address: i64, value: i64) -> Result<(), Error> {
+        self.machine.store(address, value)
+    }
+
+    /// Moves to `address`, which must not be negative.
+    pub fn jump(&self, address: i64) -> Result<Flow, Error> {
+        usize::try_from(address)
+            .map(Flow::Jump)
+            .map_err(|_| self.machine.out_of_range(address))
+    }
+
+    pub fn base(&self) -> i64 {
+        self.machine.base
+    }
+
+    pub fn set_base(&mut self, base: i64) {
+        self.machine.base = base;
+    }
+
+    pub fn input(&mut self) -> Result<i64, Error> {
+        let address = self.address();
+        self.input.next().ok_or(Error::InputExhausted { address })
+    }
+
+    pub fn output(&mut self, value: i64) {
+        (self.output)(value)
+    }
+
+    pub fn port(&mut self, port: i64) -> Result<&mut dyn Device, Error> {
+        let address = self.machine.index;
+        match self.machine.ports.get_mut(&port) {
+            Some(device) => Ok(device.as_mut()),
+            None => Err(Error::NoDevice { port, address }),
+        }
+    }
+}
+
+/// Opcodes available to a `Machine`, indexed by the opcode without its mode
+/// digits.
+#[derive(Clone)]
+pub struct InstructionSet(Vec<Option<Instruction>>);
+
+impl InstructionSet {
+    pub fn empty() -> Self {
+        InstructionSet(vec![None; 100])
+    }
+
+    /// The complete instruction set from day 9.
+    pub fn standard() -> Self {
+        use Param::{Read, Write};
+
+        let mut set = InstructionSet::empty();
+        set.register(1, "add", &[Read, Read, Write], add);
+        set.register(2, "mul", &[Read, Read, Write], mul);
+        set.register(3, "in", &[Write], inp);
+        set.register(4, "out", &[Read], out);
+        set.register(5, "jnz", &[Read, Read], jnz);
+        set.register(6, "jz", &[Read, Read], jz);
+        set.register(7, "lt", &[Read, Read, Write], lt);
+        set.register(8, "eq", &[Read, Read, Write], eq);
+        set.register(9, "arb", &[Read], arb);
+        set.register(99, "halt", &[], halt);
+        set
+    }
+
+    /// The standard set plus `inport` and `outport` for port devices. Machines
+    /// only get these when built with `Machine::with_instructions`.
+    pub fn extended() -> Self {
+        use Param::{Read, Write};
+
+        let mut set = InstructionSet::standard();
+        set.register(13, "inport", &[Read, Write], inport);
+        set.register(14, "outport", &[Read, Read], outport);
+        set
+    }
+
+    /// Adds an instruction, returning the one it replaced if any.
+    pub fn register(
+        &mut self,
+        opcode: usize,
+        name: &'static str,
+        params: &'static [Param],
+        handler: Handler,
+    ) -> Option<Instruction> {
+        assert!(opcode < 100, "opcode {} overlaps the mode digits", opcode);
+        assert!(params.len() <= MAX_PARAMS, "too many parameters for {}", name);
+        self.0[opcode].replace(Instruction { name, params, handler })
+    }
+
+    pub fn get(&self, opcode: i64) -> Option<&Instruction> {
+        if opcode < 0 {
+            return None;
+        }
+        self.0[(opcode % 100) as usize].as_ref()
+    }
+}
+
+impl Default for InstructionSet {
+    fn default() -> Self {
+        InstructionSet::standard()
+    }
+}
+
+fn add(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.store(par[2], par[0] + par[1])?;
+    Ok(Flow::Next)
+}
+
+fn mul(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.store(par[2], par[0] * par[1])?;
+    Ok(Flow::Next)
+}
+
+fn lt(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.store(par[2], (par[0] < par[1]) as i64)?;
+    Ok(Flow::Next)
+}
+
+fn eq(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.store(par[2], (par[0] == par[1]) as i64)?;
+    Ok(Flow::Next)
+}
+
+fn jnz(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    if par[0] != 0 { ctx.jump(par[1]) } else { Ok(Flow::Next) }
+}
+
+fn jz(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    if par[0] == 0 { ctx.jump(par[1]) } else { Ok(Flow::Next) }
+}
+
+fn inp(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    let value = ctx.input()?;
+    ctx.store(par[0], value)?;
+    Ok(Flow::Next)
+}
+
+fn out(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.output(par[0]);
+    Ok(Flow::Next)
+}
+
+fn arb(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.set_base(ctx.base() + par[0]);
+    Ok(Flow::Next)
+}
+
+fn halt(_: &mut Context, _: &[i64]) -> Result<Flow, Error> {
+    Ok(Flow::Halt)
+}
+
+fn inport(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    let value = ctx.port(par[0])?.read(0);
+    ctx.store(par[1], value)?;
+    Ok(Flow::Next)
+}
+
+fn outport(ctx: &mut Context, par: &[i64]) -> Result<Flow, Error> {
+    ctx.port(par[0])?.write(0, par[1]);
+    Ok(Flow::Next)
+}
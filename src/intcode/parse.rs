@@ -0,0 +1,178 @@
+use std::error;
+use std::fmt;
+
+use super::InstructionSet;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    InvalidNumber,
+    MissingValue,
+    AddressMismatch { expected: usize, found: usize },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match self.kind {
+            ParseErrorKind::InvalidNumber =>
+                write!(f, "invalid number `{}`", self.token),
+            ParseErrorKind::MissingValue =>
+                write!(f, "missing value before `{}`", self.token),
+            ParseErrorKind::AddressMismatch { expected, found } =>
+                write!(
+                    f,
+                    "address label `{}` is {} but the value is at {}",
+                    self.token, found, expected,
+                ),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+/// Parses a program in either the canonical comma separated format or the
+/// annotated listing format produced by `format_listing`. Values may be
+/// separated by commas and/or whitespace, `;` and `#` start a comment that
+/// runs to the end of the line, and a line may start with an `address:`
+/// label which must match the position of the value that follows it.
+pub fn parse_source(input: &str) -> Result<Vec<i64>, ParseError> {
+    let mut program = Vec::new();
+    // Set after a comma until the next value, so that `1,,2` is rejected
+    // while a single trailing comma (even across lines) is accepted.
+    let mut after_comma = true;
+
+    for (line, number) in input.lines().zip(1..) {
+        let code = line
+            .find([';', '#'])
+            .map_or(line, |end| &line[..end]);
+
+        let mut chars = code.char_indices().zip(1..).peekable();
+        let mut first = true;
+        while let Some(((start, c), column)) = chars.next() {
+            let error = |token: &str, kind| ParseError {
+                line: number,
+                column,
+                token: token.to_owned(),
+                kind,
+            };
+
+            if c == ',' {
+                if after_comma {
+                    return Err(error(",", ParseErrorKind::MissingValue));
+                }
+                after_comma = true;
+                continue;
+            } else if c.is_whitespace() {
+                continue;
+            }
+
+            let mut end = start + c.len_utf8();
+            while let Some(&((i, c), _)) = chars.peek() {
+                if is_separator(c) { break }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let token = &code[start..end];
+
+            if first && token.ends_with(':') {
+                let found = token[..token.len() - 1]
+                    .parse()
+                    .map_err(|_| error(token, ParseErrorKind::InvalidNumber))?;
+                if found != program.len() {
+                    return Err(error(token, ParseErrorKind::AddressMismatch {
+                        expected: program.len(),
+                        found,
+                    }));
+                }
+            } else {
+                let value = token
+                    .parse()
+                    .map_err(|_| error(token, ParseErrorKind::InvalidNumber))?;
+                program.push(value);
+                after_comma = false;
+            }
+            first = false;
+        }
+    }
+
+    Ok(program)
+}
+
+/// Formats a program in the canonical single line, comma separated format.
+pub fn format_program(program: &[i64]) -> String {
+    program
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn format_operand(value: i64, mode: i64) -> String {
+    match mode {
+        1 => value.to_string(),
+        2 => format!("[rb{:+}]", value),
+        _ => format!("[{}]", value),
+    }
+}
+
+/// Formats a program as an annotated listing with one instruction per line,
+/// each prefixed with its address and followed by a disassembly comment.
+/// Values that don't decode to a standard instruction are listed as data.
+/// The listing parses back to the same program with `parse_source`.
+pub fn format_listing(program: &[i64]) -> String {
+    format_listing_with(program, &InstructionSet::default())
+}
+
+pub fn format_listing_with(
+    program: &[i64],
+    instructions: &InstructionSet,
+) -> String {
+    let mut listing = String::new();
+    let mut index = 0;
+
+    while index < program.len() {
+        let opcode = program[index];
+        let decoded = instructions
+            .get(opcode)
+            .map(|instruction| (instruction.name, instruction.params.len()))
+            .filter(|&(_, params)| index + params < program.len());
+
+        let (len, comment) = match decoded {
+            Some((name, params)) => {
+                let operands = program[index + 1 ..= index + params]
+                    .iter()
+                    .scan(opcode / 100, |modes, &value| {
+                        let mode = *modes % 10;
+                        *modes /= 10;
+                        Some(format_operand(value, mode))
+                    })
+                    .collect::<Vec<_>>();
+                (params + 1, format!("{} {}", name, operands.join(", ")))
+            },
+            None => (1, "data".to_owned()),
+        };
+
+        let code = format!(
+            "{:04}: {}",
+            index,
+            format_program(&program[index .. index + len]),
+        );
+        listing.push_str(&format!("{:<32} ; {}\n", code, comment.trim_end()));
+
+        index += len;
+    }
+
+    listing
+}
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use super::{Error, Machine, Status};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The machine at `machine` stopped with an error.
+    Failed { machine: usize, error: Error },
+    /// The machine at `machine` panicked, with the panic's message.
+    Panicked { machine: usize, message: String },
+    /// Nothing can run: the machines in `blocked` are all waiting for input
+    /// and no running machine is left to send any.
+    Deadlock { blocked: Vec<usize> },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::Failed { machine, error } =>
+                write!(f, "machine {} failed: {}", machine, error),
+            ScheduleError::Panicked { machine, message } =>
+                write!(f, "machine {} panicked: {}", machine, message),
+            ScheduleError::Deadlock { blocked } => {
+                let blocked: Vec<String> = blocked.iter().map(usize::to_string).collect();
+                write!(f, "deadlock: machines {} are waiting for input", blocked.join(", "))
+            },
+        }
+    }
+}
+
+impl error::Error for ScheduleError {}
+
+struct Process {
+    machine: Machine,
+    inbox: VecDeque<i64>,
+    targets: Vec<usize>,
+    outputs: Vec<i64>,
+    status: Option<Status>,
+}
+
+/// Runs a set of machines that talk to each other on a single thread. Each
+/// machine's outputs are queued as input for the machines connected to it.
+///
+/// Machines take turns in the order they were added, each running until it
+/// halts or runs out of input, so a run always goes the same way.
+#[derive(Default)]
+pub struct Scheduler {
+    processes: Vec<Process>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    pub fn add(&mut self, machine: Machine) -> usize {
+        self.processes.push(Process {
+            machine,
+            inbox: VecDeque::new(),
+            targets: Vec::new(),
+            outputs: Vec::new(),
+            status: None,
+        });
+        self.processes.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.processes.len(), "no machine {}", to);
+        self.processes[from].targets.push(to);
+    }
+
+    /// Queues a value as input for a machine.
+    pub fn send(&mut self, to: usize, value: i64) {
+        self.processes[to].inbox.push_back(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    pub fn machine(&self, index: usize) -> &Machine {
+        &self.processes[index].machine
+    }
+
+    /// Everything a machine has output so far.
+    pub fn outputs(&self, index: usize) -> &[i64] {
+        &self.processes[index].outputs
+    }
+
+    /// Where a machine stopped on its last turn, if it has had one.
+    pub fn status(&self, index: usize) -> Option<Status> {
+        self.processes[index].status
+    }
+
+    /// Takes turns until every machine has halted. Stops at the first
+    /// machine to fail, or when the machines left are all waiting for input
+    /// that can't arrive.
+    pub fn run(&mut self) -> Result<(), ScheduleError> {
+        loop {
+            let mut progress = false;
+            for index in 0..self.processes.len() {
+                let process = &self.processes[index];
+                let ready = match process.status {
+                    None => true,
+                    Some(Status::Blocked) => !process.inbox.is_empty(),
+                    Some(Status::Halted) => false,
+                };
+                if ready {
+                    self.turn(index)?;
+                    progress = true;
+                }
+            }
+
+            if !progress {
+                let blocked: Vec<usize> = (0..self.processes.len())
+                    .filter(|&index| self.processes[index].status == Some(Status::Blocked))
+                    .collect();
+                return if blocked.is_empty() {
+                    Ok(())
+                } else {
+                    Err(ScheduleError::Deadlock { blocked })
+                };
+            }
+        }
+    }
+
+    fn turn(&mut self, index: usize) -> Result<(), ScheduleError> {
+        let process = &mut self.processes[index];
+        let mut sent = Vec::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            process.machine.resume(&mut process.inbox, |value| sent.push(value))
+        }));
+
+        process.outputs.extend(&sent);
+        let status = match result {
+            Ok(Ok(status)) => status,
+            Ok(Err(error)) => return Err(ScheduleError::Failed { machine: index, error }),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                return Err(ScheduleError::Panicked { machine: index, message });
+            },
+        };
+        process.status = Some(status);
+
+        for target in process.targets.clone() {
+            self.processes[target].inbox.extend(&sent);
+        }
+        Ok(())
+    }
+}
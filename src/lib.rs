@@ -1,17 +1,26 @@
 use aoc_runner_derive::aoc_lib;
 
-mod intcode;
+pub mod amplifier;
+pub mod geometry;
+pub mod grid;
+pub mod image;
+pub mod intcode;
+pub mod ocr;
+pub mod orbit;
+pub mod robot;
+pub mod sif;
+pub mod wire;
 
-mod day1;
+pub mod day1;
 mod day2;
-mod day3;
-mod day4;
+pub mod day3;
+pub mod day4;
 mod day5;
-mod day6;
+pub mod day6;
 mod day7;
-mod day8;
+pub mod day8;
 mod day9;
 mod day10;
-mod day11;
+pub mod day11;
 
 aoc_lib!{year = 2019}
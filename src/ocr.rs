@@ -0,0 +1,221 @@
+use std::error;
+use std::fmt;
+
+// Glyphs from the puzzles that draw letters, keyed by the letter. Each glyph
+// is drawn left aligned in a cell of the font's pitch, with blank columns
+// filling the rest.
+const SMALL: (usize, &[(char, &str)]) = (5, &[
+    ('A', ".##. #..# #..# #### #..# #..#"),
+    ('B', "###. #..# ###. #..# #..# ###."),
+    ('C', ".##. #..# #... #... #..# .##."),
+    ('E', "#### #... ###. #... #... ####"),
+    ('F', "#### #... ###. #... #... #..."),
+    ('G', ".##. #..# #... #.## #..# .###"),
+    ('H', "#..# #..# #### #..# #..# #..#"),
+    ('I', ".### ..#. ..#. ..#. ..#. .###"),
+    ('J', "..## ...# ...# ...# #..# .##."),
+    ('K', "#..# #.#. ##.. #.#. #.#. #..#"),
+    ('L', "#... #... #... #... #... ####"),
+    ('O', ".##. #..# #..# #..# #..# .##."),
+    ('P', "###. #..# #..# ###. #... #..."),
+    ('R', "###. #..# #..# ###. #.#. #..#"),
+    ('S', ".### #... #... .##. ...# ###."),
+    ('U', "#..# #..# #..# #..# #..# .##."),
+    ('Y', "#...# #...# .#.#. ..#.. ..#.. ..#.."),
+    ('Z', "#### ...# ..#. .#.. #... ####"),
+]);
+
+const LARGE: (usize, &[(char, &str)]) = (8, &[
+    ('A', "..##.. .#..#. #....# #....# #....# ###### #....# #....# #....# #....#"),
+    ('B', "#####. #....# #....# #....# #####. #....# #....# #....# #....# #####."),
+    ('C', ".####. #....# #..... #..... #..... #..... #..... #..... #....# .####."),
+    ('E', "###### #..... #..... #..... #####. #..... #..... #..... #..... ######"),
+    ('F', "###### #..... #..... #..... #####. #..... #..... #..... #..... #....."),
+    ('G', ".####. #....# #..... #..... #..... #..### #....# #....# #...## .###.#"),
+    ('H', "#....# #....# #....# #....# ###### #....# #....# #....# #....# #....#"),
+    ('J', "...### ....#. ....#. ....#. ....#. ....#. ....#. #...#. #...#. .###.."),
+    ('K', "#....# #...#. #..#.. #.#... ##.... ##.... #.#... #..#.. #...#. #....#"),
+    ('L', "#..... #..... #..... #..... #..... #..... #..... #..... #..... ######"),
+    ('N', "#....# ##...# ##...# #.#..# #.#..# #..#.# #..#.# #...## #...## #....#"),
+    ('P', "#####. #....# #....# #....# #####. #..... #..... #..... #..... #....."),
+    ('R', "#####. #....# #....# #....# #####. #..#.. #...#. #...#. #....# #....#"),
+    ('X', "#....# #....# .#..#. .#..#. ..##.. ..##.. .#..#. .#..#. #....# #....#"),
+    ('Z', "###### .....# .....# ....#. ...#.. ..#... .#.... #..... #..... ######"),
+]);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Only glyphs six and ten pixels tall are known.
+    UnsupportedHeight(usize),
+    /// The glyph at `index` in the text didn't match any known letter.
+    UnknownGlyph { index: usize, bitmap: Vec<Vec<bool>> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnsupportedHeight(height) =>
+                write!(f, "no font for letters {} pixels tall", height),
+            Error::UnknownGlyph { index, bitmap } => {
+                write!(f, "unknown glyph at position {}:", index)?;
+                for row in bitmap {
+                    writeln!(f)?;
+                    for &pixel in row {
+                        write!(f, "{}", if pixel { '#' } else { '.' })?;
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+fn matches(glyph: &str, cell: &[Vec<bool>]) -> bool {
+    glyph
+        .split(' ')
+        .zip(cell)
+        .all(|(pattern, row)| {
+            let pattern = pattern.bytes().map(|b| b == b'#');
+            row.iter().copied().eq(
+                pattern.chain(std::iter::repeat(false)).take(row.len())
+            )
+        })
+}
+
+/// Reads the letters drawn by lit pixels in `grid`, which is a list of rows.
+/// Blank rows and columns around the text are ignored.
+pub fn recognise(grid: &[Vec<bool>]) -> Result<String, Error> {
+    let lit_rows: Vec<_> = grid
+        .iter()
+        .filter(|row| row.iter().any(|&pixel| pixel))
+        .collect();
+    let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+    let lit_columns = (0..width)
+        .filter(|&x| lit_rows.iter().any(|row| row.get(x) == Some(&true)));
+    let (x1, x2) = lit_columns
+        .fold((width, 0), |(x1, x2), x| (x1.min(x), x2.max(x + 1)));
+    if x1 >= x2 {
+        return Ok(String::new());
+    }
+
+    let (pitch, font) = match lit_rows.len() {
+        6 => SMALL,
+        10 => LARGE,
+        height => return Err(Error::UnsupportedHeight(height)),
+    };
+
+    // The text may have been cropped on the left, so try every alignment of
+    // the cells and keep the one that reads furthest before failing.
+    let mut best: Option<Result<String, Error>> = None;
+    for offset in 0..pitch {
+        let cells = (offset + x2 - x1).div_ceil(pitch);
+        let result = (0..cells)
+            .map(|index| {
+                let cell: Vec<Vec<bool>> = lit_rows
+                    .iter()
+                    .map(|row| {
+                        (0..pitch)
+                            .map(|x| {
+                                (index * pitch + x + x1)
+                                    .checked_sub(offset)
+                                    .filter(|&x| x >= x1 && x < x2)
+                                    .and_then(|x| row.get(x))
+                                    == Some(&true)
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                font.iter()
+                    .find(|(_, glyph)| matches(glyph, &cell))
+                    .map(|&(letter, _)| letter)
+                    .ok_or(Error::UnknownGlyph { index, bitmap: cell })
+            })
+            .collect::<Result<String, Error>>();
+
+        let progress = |result: &Result<String, Error>| match result {
+            Ok(_) => usize::MAX,
+            Err(Error::UnknownGlyph { index, .. }) => *index,
+            Err(_) => 0,
+        };
+        if best.as_ref().is_none_or(|best| progress(&result) > progress(best)) {
+            best = Some(result);
+        }
+    }
+
+    best.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws `text` in a font, one glyph per cell of the font's pitch.
+    fn render((pitch, font): (usize, &[(char, &str)]), text: &str) -> Vec<Vec<bool>> {
+        let height = font[0].1.split(' ').count();
+        let mut grid = vec![vec![false; pitch * text.len()]; height];
+        for (i, letter) in text.chars().enumerate() {
+            let &(_, glyph) = font.iter().find(|&&(c, _)| c == letter).unwrap();
+            for (row, pattern) in grid.iter_mut().zip(glyph.split(' ')) {
+                for (x, b) in pattern.bytes().enumerate() {
+                    row[i * pitch + x] = b == b'#';
+                }
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn reads_every_glyph() {
+        for &font in &[SMALL, LARGE] {
+            let letters: String = font.1.iter().map(|&(letter, _)| letter).collect();
+            for letter in letters.chars() {
+                let text = letter.to_string();
+                assert_eq!(recognise(&render(font, &text)), Ok(text));
+            }
+            assert_eq!(recognise(&render(font, &letters)), Ok(letters));
+        }
+    }
+
+    #[test]
+    fn ignores_blank_margins() {
+        let mut grid = render(SMALL, "HI");
+        for row in &mut grid {
+            row.splice(0..0, vec![false; 3]);
+        }
+        grid.insert(0, vec![false; grid[0].len()]);
+        assert_eq!(recognise(&grid), Ok("HI".to_string()));
+    }
+
+    #[test]
+    fn reads_text_cropped_on_the_left() {
+        // `I` starts with a blank column, so cutting it off leaves the rest
+        // of the text out of step with the cells.
+        let mut grid = render(SMALL, "IZJ");
+        for row in &mut grid {
+            row.remove(0);
+        }
+        assert_eq!(recognise(&grid), Ok("IZJ".to_string()));
+    }
+
+    #[test]
+    fn reports_unknown_glyphs() {
+        let mut grid = render(SMALL, "OK");
+        for row in &mut grid {
+            row.extend(vec![true, true, true, true, false]);
+        }
+        match recognise(&grid) {
+            Err(Error::UnknownGlyph { index, bitmap }) => {
+                assert_eq!(index, 2);
+                assert_eq!(bitmap, vec![vec![true, true, true, true, false]; 6]);
+            },
+            result => panic!("expected an unknown glyph, got {:?}", result),
+        }
+        assert_eq!(
+            recognise(&vec![vec![true]; 7]),
+            Err(Error::UnsupportedHeight(7)),
+        );
+    }
+}
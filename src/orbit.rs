@@ -0,0 +1,454 @@
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+
+mod export;
+
+pub use export::{dot, tree, TreeOptions};
+
+/// The body every other body should orbit, directly or indirectly.
+pub const ROOT: &str = "COM";
+
+/// Something wrong with an orbit map. Lines are numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// The line isn't two names separated by a single `)`.
+    Malformed { line: usize, text: String },
+    /// `body` was already given a parent on `first_line`. The later orbit is
+    /// ignored.
+    DuplicateParent { line: usize, body: String, first_line: usize },
+    /// The bodies orbit each other in a loop, each orbiting the one before
+    /// and the first orbiting the last. `line` is the last orbit of the loop.
+    Cycle { line: usize, bodies: Vec<String> },
+    /// Nothing is named `ROOT`, or it orbits something. `roots` are the
+    /// bodies that don't orbit anything.
+    MissingRoot { roots: Vec<String> },
+    /// `root`, first seen on `line`, orbits nothing but isn't `ROOT`, so its
+    /// bodies aren't connected to the rest.
+    Disconnected { line: usize, root: String },
+}
+
+impl Issue {
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            Issue::Malformed { line, .. }
+            | Issue::DuplicateParent { line, .. }
+            | Issue::Cycle { line, .. }
+            | Issue::Disconnected { line, .. } => Some(line),
+            Issue::MissingRoot { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::Malformed { line, text } =>
+                write!(f, "line {}: expected A)B but found {:?}", line, text),
+            Issue::DuplicateParent { line, body, first_line } =>
+                write!(
+                    f,
+                    "line {}: {} already orbits something on line {}",
+                    line, body, first_line,
+                ),
+            Issue::Cycle { line, bodies } =>
+                write!(
+                    f,
+                    "line {}: orbits form a cycle {}){}",
+                    line, bodies.join(")"), bodies[0],
+                ),
+            Issue::MissingRoot { roots } if roots.is_empty() =>
+                write!(f, "no {} and no other root", ROOT),
+            Issue::MissingRoot { roots } =>
+                write!(f, "no {}, the roots are {}", ROOT, roots.join(", ")),
+            Issue::Disconnected { line, root } =>
+                write!(f, "line {}: {} doesn't orbit {}", line, root, ROOT),
+        }
+    }
+}
+
+/// Every issue found in an orbit map, in line order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub issues: Vec<Issue>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ValidationError {}
+
+/// The bodies of an orbit map as a tree rooted at `ROOT`, with every body
+/// orbiting its parent. Bodies are numbered in the order they first appear,
+/// and depths and subtree sizes are worked out once when the map is built.
+#[derive(Clone, Debug, Default)]
+pub struct OrbitMap {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    depths: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl OrbitMap {
+    /// Reads `A)B` lines, each saying that `B` orbits `A`. Blank lines are
+    /// skipped. The map is checked before anything is worked out from it,
+    /// and every issue found is returned.
+    pub fn parse(input: &str) -> Result<Self, ValidationError> {
+        let mut issues = Vec::new();
+        let mut orbits = Vec::new();
+        for (text, line) in input.lines().zip(1..) {
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            match text.split(')').collect::<Vec<_>>()[..] {
+                [inner, outer] if !inner.is_empty() && !outer.is_empty() =>
+                    orbits.push((line, inner, outer)),
+                _ => issues.push(Issue::Malformed { line, text: text.to_string() }),
+            }
+        }
+
+        OrbitMap::build(orbits, issues)
+    }
+
+    /// Builds the tree from `(inner, outer)` pairs, where `outer` orbits
+    /// `inner`. Issues give the position of the pair, counting from 1.
+    pub fn from_orbits<'a, I>(orbits: I) -> Result<Self, ValidationError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let orbits = orbits
+            .into_iter()
+            .zip(1..)
+            .map(|((inner, outer), line)| (line, inner, outer))
+            .collect();
+
+        OrbitMap::build(orbits, Vec::new())
+    }
+
+    fn build(
+        orbits: Vec<(usize, &str, &str)>,
+        mut issues: Vec<Issue>,
+    ) -> Result<Self, ValidationError> {
+        let mut map = OrbitMap::default();
+        let mut seen = Vec::new();
+        let mut parent_lines = Vec::new();
+        for (line, inner, outer) in orbits {
+            // A rejected orbit doesn't add its bodies, so that they aren't
+            // reported again as disconnected.
+            if let Some(outer) = map.id(outer).filter(|&outer| map.parents[outer].is_some()) {
+                issues.push(Issue::DuplicateParent {
+                    line,
+                    body: map.names[outer].clone(),
+                    first_line: parent_lines[outer],
+                });
+                continue;
+            }
+
+            let [inner, outer] = [inner, outer].map(|name| {
+                if !map.contains(name) {
+                    seen.push(line);
+                    parent_lines.push(0);
+                }
+                map.intern(name)
+            });
+            map.parents[outer] = Some(inner);
+            parent_lines[outer] = line;
+        }
+        for (body, parent) in map.parents.iter().enumerate() {
+            if let Some(parent) = *parent {
+                map.children[parent].push(body);
+            }
+        }
+
+        let order = map.order();
+        issues.extend(map.cycles(&order, &parent_lines));
+        let roots: Vec<usize> = (0..map.len())
+            .filter(|&body| map.parents[body].is_none())
+            .collect();
+        match map.id(ROOT).filter(|&root| map.parents[root].is_none()) {
+            Some(root) => issues.extend(
+                roots
+                    .iter()
+                    .filter(|&&body| body != root)
+                    .map(|&body| Issue::Disconnected {
+                        line: seen[body],
+                        root: map.names[body].clone(),
+                    }),
+            ),
+            None => issues.push(Issue::MissingRoot {
+                roots: roots.iter().map(|&body| map.names[body].clone()).collect(),
+            }),
+        }
+
+        if !issues.is_empty() {
+            issues.sort_by_key(|issue| issue.line().unwrap_or(usize::MAX));
+            return Err(ValidationError { issues });
+        }
+
+        map.depths = vec![0; map.len()];
+        map.sizes = vec![1; map.len()];
+        for &body in &order {
+            if let Some(parent) = map.parents[body] {
+                map.depths[body] = map.depths[parent] + 1;
+            }
+        }
+        for &body in order.iter().rev() {
+            if let Some(parent) = map.parents[body] {
+                map.sizes[parent] += map.sizes[body];
+            }
+        }
+        Ok(map)
+    }
+
+    // Follows parents from the bodies no root leads to, which must end up
+    // going round a cycle.
+    fn cycles(&self, order: &[usize], parent_lines: &[usize]) -> Vec<Issue> {
+        // 0 for unvisited, 1 for on the current walk and 2 for done.
+        let mut state = vec![0; self.len()];
+        order.iter().for_each(|&body| state[body] = 2);
+
+        let mut cycles = Vec::new();
+        for start in 0..self.len() {
+            let mut walk = Vec::new();
+            let mut body = start;
+            while state[body] == 0 {
+                state[body] = 1;
+                walk.push(body);
+                body = self.parents[body].unwrap();
+            }
+            if state[body] == 1 {
+                let cycle = &walk[walk.iter().position(|&b| b == body).unwrap()..];
+                cycles.push(Issue::Cycle {
+                    line: cycle.iter().map(|&b| parent_lines[b]).max().unwrap(),
+                    bodies: cycle.iter().rev().map(|&b| self.names[b].clone()).collect(),
+                });
+            }
+            walk.iter().for_each(|&b| state[b] = 2);
+        }
+        cycles
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.parents.push(None);
+        self.children.push(Vec::new());
+        id
+    }
+
+    // The bodies reachable from the roots, breadth first, so every body
+    // comes after its parent.
+    fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.len());
+        let mut queue: VecDeque<usize> = (0..self.len())
+            .filter(|&body| self.parents[body].is_none())
+            .collect();
+        while let Some(body) = queue.pop_front() {
+            order.push(body);
+            queue.extend(&self.children[body]);
+        }
+        order
+    }
+
+    fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn contains(&self, body: &str) -> bool {
+        self.ids.contains_key(body)
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// The bodies that don't orbit anything.
+    pub fn roots(&self) -> impl Iterator<Item = &str> {
+        self.bodies()
+            .zip(&self.parents)
+            .filter(|(_, parent)| parent.is_none())
+            .map(|(name, _)| name)
+    }
+
+    /// The bodies that nothing orbits.
+    pub fn leaves(&self) -> impl Iterator<Item = &str> {
+        self.bodies()
+            .zip(&self.children)
+            .filter(|(_, children)| children.is_empty())
+            .map(|(name, _)| name)
+    }
+
+    pub fn parent(&self, body: &str) -> Option<&str> {
+        let parent = self.parents[self.id(body)?]?;
+        Some(&self.names[parent])
+    }
+
+    pub fn children(&self, body: &str) -> impl Iterator<Item = &str> {
+        let children = match self.id(body) {
+            Some(id) => self.children[id].as_slice(),
+            None => &[],
+        };
+        children.iter().map(move |&child| self.names[child].as_str())
+    }
+
+    /// How many bodies `body` orbits, directly and indirectly.
+    pub fn depth(&self, body: &str) -> Option<usize> {
+        self.id(body).map(|id| self.depths[id])
+    }
+
+    /// How many bodies are in the subtree rooted at `body`, including it.
+    pub fn subtree_size(&self, body: &str) -> Option<usize> {
+        self.id(body).map(|id| self.sizes[id])
+    }
+
+    /// The direct and indirect orbits of every body.
+    pub fn total_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    fn lca(&self, mut a: usize, mut b: usize) -> Option<usize> {
+        while self.depths[a] > self.depths[b] {
+            a = self.parents[a]?;
+        }
+        while self.depths[b] > self.depths[a] {
+            b = self.parents[b]?;
+        }
+        while a != b {
+            a = self.parents[a]?;
+            b = self.parents[b]?;
+        }
+        Some(a)
+    }
+
+    /// The deepest body that both bodies are in the subtree of. Bodies under
+    /// different roots have none.
+    pub fn common_ancestor(&self, a: &str, b: &str) -> Option<&str> {
+        let lca = self.lca(self.id(a)?, self.id(b)?)?;
+        Some(&self.names[lca])
+    }
+
+    /// The bodies from `a` to `b` through the tree, both included.
+    pub fn path(&self, a: &str, b: &str) -> Option<Vec<&str>> {
+        let (a, b) = (self.id(a)?, self.id(b)?);
+        let lca = self.lca(a, b)?;
+
+        let climb = |mut body: usize| {
+            let mut steps = Vec::new();
+            while body != lca {
+                steps.push(self.names[body].as_str());
+                body = self.parents[body].unwrap();
+            }
+            steps
+        };
+        let mut path = climb(a);
+        path.push(&self.names[lca]);
+        path.extend(climb(b).into_iter().rev());
+        Some(path)
+    }
+
+    /// The orbital transfers needed to move from the body `a` orbits to the
+    /// body `b` orbits.
+    pub fn transfers(&self, a: &str, b: &str) -> Option<usize> {
+        let (a, b) = (self.parents[self.id(a)?]?, self.parents[self.id(b)?]?);
+        let lca = self.lca(a, b)?;
+        Some(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(input: &str) -> Vec<Issue> {
+        OrbitMap::parse(input).unwrap_err().issues
+    }
+
+    #[test]
+    fn puzzle_examples() {
+        let map = OrbitMap::parse("COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L").unwrap();
+        assert_eq!(map.total_orbits(), 42);
+
+        let map = OrbitMap::parse(
+            "COM)B\nB)C\nC)D\nD)E\nE)F\nB)G\nG)H\nD)I\nE)J\nJ)K\nK)L\nK)YOU\nI)SAN",
+        ).unwrap();
+        assert_eq!(map.transfers("YOU", "SAN"), Some(4));
+        assert_eq!(map.common_ancestor("YOU", "SAN"), Some("D"));
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        assert_eq!(issues("COM)B\nB-C\n\nC)"), vec![
+            Issue::Malformed { line: 2, text: "B-C".to_string() },
+            Issue::Malformed { line: 4, text: "C)".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn reports_duplicate_parents() {
+        assert_eq!(issues("COM)B\nB)C\nCOM)C"), vec![
+            Issue::DuplicateParent { line: 3, body: "C".to_string(), first_line: 2 },
+        ]);
+    }
+
+    #[test]
+    fn rejected_orbits_add_no_bodies() {
+        assert_eq!(issues("COM)B\nB)Z\nA)Z"), vec![
+            Issue::DuplicateParent { line: 3, body: "Z".to_string(), first_line: 2 },
+        ]);
+    }
+
+    #[test]
+    fn reports_cycles() {
+        assert_eq!(issues("COM)B\nX)Y\nY)Z\nZ)X"), vec![
+            Issue::Cycle {
+                line: 4,
+                bodies: vec!["Y".to_string(), "Z".to_string(), "X".to_string()],
+            },
+        ]);
+        assert_eq!(issues("COM)B\nH)H"), vec![
+            Issue::Cycle { line: 2, bodies: vec!["H".to_string()] },
+        ]);
+    }
+
+    #[test]
+    fn reports_missing_roots() {
+        assert_eq!(issues("A)B\nB)C"), vec![
+            Issue::MissingRoot { roots: vec!["A".to_string()] },
+        ]);
+        assert_eq!(issues("X)COM\nCOM)X"), vec![
+            Issue::Cycle { line: 2, bodies: vec!["COM".to_string(), "X".to_string()] },
+            Issue::MissingRoot { roots: Vec::new() },
+        ]);
+    }
+
+    #[test]
+    fn reports_disconnected_roots() {
+        assert_eq!(issues("COM)B\nA)C\nC)D"), vec![
+            Issue::Disconnected { line: 2, root: "A".to_string() },
+        ]);
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::OrbitMap;
+
+#[derive(Clone, Debug, Default)]
+pub struct TreeOptions<'a> {
+    /// Marks the path between two bodies, such as `("YOU", "SAN")`.
+    pub highlight: Option<(&'a str, &'a str)>,
+    /// Draws runs of at least this many bodies that each have a single
+    /// child as one node. Zero leaves every body on its own.
+    pub collapse: usize,
+}
+
+// A body, or a run of bodies each orbited by just the next, drawn as one.
+struct Node {
+    bodies: Vec<usize>,
+    highlighted: bool,
+}
+
+struct Layout<'a> {
+    map: &'a OrbitMap,
+    path: HashSet<usize>,
+    collapse: usize,
+}
+
+impl<'a> Layout<'a> {
+    fn new(map: &'a OrbitMap, options: &TreeOptions) -> Self {
+        let path = options.highlight
+            .and_then(|(a, b)| map.path(a, b))
+            .map_or_else(HashSet::new, |path| {
+                path.iter().filter_map(|body| map.id(body)).collect()
+            });
+
+        Layout { map, path, collapse: options.collapse }
+    }
+
+    fn node(&self, body: usize) -> Node {
+        let mut bodies = vec![body];
+        let mut last = body;
+        while let [child] = self.map.children[last][..] {
+            bodies.push(child);
+            last = child;
+        }
+        if self.collapse == 0 || bodies.len() < self.collapse {
+            bodies.truncate(1);
+        }
+
+        let highlighted = bodies.iter().any(|body| self.path.contains(body));
+        Node { bodies, highlighted }
+    }
+
+    fn children(&self, node: &Node) -> &'a [usize] {
+        &self.map.children[*node.bodies.last().unwrap()]
+    }
+
+    fn on_path(&self, from: &Node, to: &Node) -> bool {
+        self.path.contains(from.bodies.last().unwrap()) && self.path.contains(&to.bodies[0])
+    }
+
+    fn label(&self, node: &Node) -> String {
+        let names = &self.map.names;
+        match node.bodies[..] {
+            [body] => names[body].clone(),
+            [first, .., last] => format!(
+                "{} … {}, {} bodies",
+                names[first], names[last], node.bodies.len(),
+            ),
+            [] => unreachable!(),
+        }
+    }
+
+    fn depths(&self, node: &Node) -> String {
+        let depths = &self.map.depths;
+        match node.bodies[..] {
+            [body] => format!("{}", depths[body]),
+            [first, .., last] => format!("{}-{}", depths[first], depths[last]),
+            [] => unreachable!(),
+        }
+    }
+
+    fn roots(&self) -> Vec<usize> {
+        (0..self.map.len())
+            .filter(|&body| self.map.parents[body].is_none())
+            .collect()
+    }
+}
+
+/// Draws the orbits as an indented tree, each body followed by its depth.
+/// Bodies on the highlighted path are marked with `*`.
+pub fn tree(map: &OrbitMap, options: &TreeOptions) -> String {
+    let layout = Layout::new(map, options);
+    let mut out = String::new();
+
+    // Each entry is a body and the prefixes for its own line and for the
+    // lines of its children.
+    let mut stack: Vec<(usize, String, String)> = layout
+        .roots()
+        .into_iter()
+        .rev()
+        .map(|root| (root, String::new(), String::new()))
+        .collect();
+    while let Some((body, prefix, indent)) = stack.pop() {
+        let node = layout.node(body);
+        let _ = writeln!(
+            out,
+            "{}{} ({}){}",
+            prefix,
+            layout.label(&node),
+            layout.depths(&node),
+            if node.highlighted { " *" } else { "" },
+        );
+
+        let children = layout.children(&node);
+        for (i, &child) in children.iter().enumerate().rev() {
+            let last = i + 1 == children.len();
+            stack.push((
+                child,
+                format!("{}{}", indent, if last { "└── " } else { "├── " }),
+                format!("{}{}", indent, if last { "    " } else { "│   " }),
+            ));
+        }
+    }
+    out
+}
+
+/// Writes the orbits as a Graphviz digraph, with edges from each body to
+/// the bodies orbiting it. The highlighted path is drawn in red.
+pub fn dot(map: &OrbitMap, options: &TreeOptions) -> String {
+    let layout = Layout::new(map, options);
+    let mut out = String::from("digraph orbits {\n    node [shape=box];\n");
+
+    let mut stack = layout.roots();
+    while let Some(body) = stack.pop() {
+        let node = layout.node(body);
+        let _ = write!(
+            out,
+            "    n{} [label=\"{}\\ndepth {}\"",
+            body,
+            layout.label(&node).replace('"', "\\\""),
+            layout.depths(&node),
+        );
+        if node.highlighted {
+            out.push_str(" color=red fontcolor=red");
+        }
+        out.push_str("];\n");
+
+        for &child in layout.children(&node) {
+            let _ = write!(out, "    n{} -> n{}", body, child);
+            if layout.on_path(&node, &layout.node(child)) {
+                out.push_str(" [color=red penwidth=2]");
+            }
+            out.push_str(";\n");
+            stack.push(child);
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
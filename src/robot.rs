@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::iter;
+
+use crate::geometry::{Direction, Point, Turn};
+use crate::grid::{Grid, SparseGrid};
+use crate::intcode::{self, Machine};
+
+mod replay;
+
+pub use replay::{PlayOptions, Replay};
+
+#[derive(Clone, Debug, Default)]
+pub struct Panel {
+    pub visits: usize,
+    /// Every value painted here, with the step of the path it happened on.
+    pub paints: Vec<(usize, i64)>,
+}
+
+impl Panel {
+    pub fn color(&self) -> Option<i64> {
+        self.paints.last().map(|&(_, color)| color)
+    }
+}
+
+/// Everything a robot has done: each position and heading it has been in,
+/// and the panels it has visited or painted along the way.
+#[derive(Clone, Debug, Default)]
+pub struct World {
+    pub panels: SparseGrid<Panel>,
+    pub path: Vec<(Point, Direction)>,
+}
+
+impl World {
+    pub fn panel(&self, position: Point) -> Option<&Panel> {
+        self.panels.get(position)
+    }
+
+    pub fn color(&self, position: Point) -> Option<i64> {
+        self.panel(position).and_then(Panel::color)
+    }
+
+    pub fn painted(&self) -> impl Iterator<Item = (Point, i64)> + '_ {
+        self.panels
+            .iter()
+            .filter_map(|(position, panel)| {
+                panel.color().map(|color| (position, color))
+            })
+    }
+
+    pub fn steps(&self) -> usize {
+        self.path.len() - 1
+    }
+}
+
+pub struct Robot {
+    pub position: Point,
+    pub heading: Direction,
+    pub world: World,
+}
+
+impl Robot {
+    pub fn new(position: Point, heading: Direction) -> Self {
+        let mut robot = Robot {
+            position,
+            heading,
+            world: World::default(),
+        };
+        robot.visit();
+        robot
+    }
+
+    fn visit(&mut self) {
+        self.world.path.push((self.position, self.heading));
+        self.world.panels
+            .get_or_insert_with(self.position, Panel::default)
+            .visits += 1;
+    }
+
+    pub fn turn(&mut self, turn: Turn) {
+        self.heading = self.heading.turn(turn);
+    }
+
+    pub fn forward(&mut self) {
+        self.position += self.heading;
+        self.visit();
+    }
+
+    pub fn paint(&mut self, color: i64) {
+        let step = self.world.steps();
+        self.world.panels
+            .get_or_insert_with(self.position, Panel::default)
+            .paints
+            .push((step, color));
+    }
+}
+
+/// How a robot program talks to its body. Whenever the program asks for
+/// input it is given a sensor reading, and every `command_len` outputs are
+/// handed to `act` as one command.
+pub trait Protocol {
+    fn command_len(&self) -> usize;
+    fn sense(&mut self, robot: &Robot) -> i64;
+    fn act(&mut self, robot: &mut Robot, command: &[i64]);
+}
+
+pub struct RobotController<P> {
+    machine: Machine,
+    protocol: P,
+    robot: Robot,
+}
+
+impl<P: Protocol> RobotController<P> {
+    pub fn new(program: Vec<i64>, protocol: P) -> Self {
+        RobotController {
+            machine: Machine::new(program),
+            protocol,
+            robot: Robot::new(Point::ORIGIN, Direction::Up),
+        }
+    }
+
+    pub fn robot(&self) -> &Robot {
+        &self.robot
+    }
+
+    pub fn into_robot(self) -> Robot {
+        self.robot
+    }
+
+    pub fn run(&mut self) -> Result<(), intcode::Error> {
+        let state = RefCell::new(
+            (&mut self.protocol, &mut self.robot, Vec::new())
+        );
+
+        let input = iter::from_fn(|| {
+            let (protocol, robot, _) = &mut *state.borrow_mut();
+            Some(protocol.sense(robot))
+        });
+
+        self.machine.run(input, |value| {
+            let (protocol, robot, command) = &mut *state.borrow_mut();
+            command.push(value);
+            if command.len() == protocol.command_len() {
+                protocol.act(robot, command);
+                command.clear();
+            }
+        })
+    }
+}
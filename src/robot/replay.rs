@@ -0,0 +1,288 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::thread;
+use std::time::Duration;
+
+use super::World;
+use crate::geometry::{Direction, Point};
+use crate::grid::{Bounds, Grid};
+
+/// Steps back through a robot's recorded run. Frame `n` shows the robot at
+/// the `n`th position of its path, with everything painted up to then.
+/// `day11::replay` plays the hull painting robot's run.
+///
+/// ```
+/// use advent_of_code_2019::geometry::{Direction, Point, Turn};
+/// use advent_of_code_2019::robot::{Replay, Robot};
+///
+/// let mut robot = Robot::new(Point::ORIGIN, Direction::Up);
+/// robot.paint(1);
+/// robot.turn(Turn::Right);
+/// robot.forward();
+///
+/// let mut frames = Vec::new();
+/// Replay::new(&robot.world).dump(&mut frames).unwrap();
+/// assert_eq!(
+///     String::from_utf8(frames).unwrap(),
+///     "step 0/1  painted 1\n^ \n\x0c\nstep 1/1  painted 1\n█>\n\x0c\n",
+/// );
+/// ```
+pub struct Replay<'a> {
+    world: &'a World,
+    bounds: Bounds,
+}
+
+pub struct PlayOptions {
+    pub delay: Duration,
+    pub paused: bool,
+}
+
+impl Default for PlayOptions {
+    fn default() -> Self {
+        PlayOptions {
+            delay: Duration::from_millis(50),
+            paused: false,
+        }
+    }
+}
+
+fn arrow(direction: Direction) -> char {
+    match direction {
+        Direction::Right => '>',
+        Direction::Up => '^',
+        Direction::Left => '<',
+        Direction::Down => 'v',
+    }
+}
+
+fn glyph(color: i64) -> char {
+    match color {
+        0 => '.',
+        1 => '█',
+        _ => std::char::from_digit((color % 36) as u32, 36).unwrap_or('?'),
+    }
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(world: &'a World) -> Self {
+        // Bounds cover the whole run so that frames don't shift around. The
+        // robot always visits its starting panel, so there is at least one.
+        Replay {
+            world,
+            bounds: world.panels.bounds().unwrap(),
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.world.path.len()
+    }
+
+    pub fn frame(&self, step: usize) -> String {
+        let (position, heading) = self.world.path[step];
+        let painted = self.world.panels
+            .iter()
+            .filter(|(_, panel)| panel.paints.iter().any(|&(n, _)| n <= step))
+            .count();
+
+        let mut frame = format!(
+            "step {}/{}  painted {}\n",
+            step,
+            self.frames() - 1,
+            painted,
+        );
+
+        for y in self.bounds.y1 ..= self.bounds.y2 {
+            for x in self.bounds.x1 ..= self.bounds.x2 {
+                let c = if Point::new(x, y) == position {
+                    arrow(heading)
+                } else {
+                    self.world
+                        .panel(Point::new(x, y))
+                        .and_then(|panel| {
+                            panel.paints
+                                .iter()
+                                .take_while(|&&(n, _)| n <= step)
+                                .last()
+                        })
+                        .map_or(' ', |&(_, color)| glyph(color))
+                };
+                frame.push(c);
+            }
+            frame.push('\n');
+        }
+
+        frame
+    }
+
+    /// Writes every frame, separated by form feeds, for reviewing offline.
+    pub fn dump<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for step in 0..self.frames() {
+            writeln!(out, "{}\x0c", self.frame(step))?;
+        }
+        out.flush()
+    }
+
+    /// Animates the run on the terminal. Pressing enter while playing pauses.
+    /// While paused, enter steps one frame, `c` continues, `+` and `-` change
+    /// the speed, a number jumps to that step and `q` quits.
+    ///
+    /// Commands are read from stdin on the calling thread, so nothing more is
+    /// read once playing stops, though lines that arrived along with the
+    /// last command are dropped. Stdin is only polled between frames on Unix;
+    /// elsewhere the controls work only while paused.
+    pub fn play(&self, options: PlayOptions) -> io::Result<()> {
+        let stdin = io::stdin();
+        let mut commands = Commands::new(stdin.lock());
+
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let PlayOptions { mut delay, mut paused } = options;
+        let mut step = 0;
+
+        while step < self.frames() {
+            write!(out, "\x1b[2J\x1b[H{}", self.frame(step))?;
+            if paused {
+                write!(out, "[paused] enter: step, c: continue, +/-: speed, q: quit ")?;
+            }
+            out.flush()?;
+
+            let command = if paused {
+                commands.next()?
+            } else {
+                commands.next_within(delay)?
+            };
+
+            match command.as_deref() {
+                None if paused => break,
+                None => step += 1,
+                Some("") if !paused => paused = true,
+                Some("") => step += 1,
+                Some("c") => paused = false,
+                Some("+") => delay /= 2,
+                Some("-") => delay = delay.checked_mul(2).unwrap_or(delay),
+                Some("q") => break,
+                Some(n) => if let Ok(n) = n.parse::<usize>() {
+                    step = n.min(self.frames() - 1);
+                },
+            }
+        }
+
+        writeln!(out)
+    }
+}
+
+// Lines typed while a replay plays. Whatever stdin has is taken in one go
+// and split into lines here, since lines left in its buffer wouldn't wake
+// up `ready`.
+struct Commands<'a> {
+    stdin: io::StdinLock<'a>,
+    lines: VecDeque<String>,
+    partial: String,
+    closed: bool,
+}
+
+impl<'a> Commands<'a> {
+    fn new(stdin: io::StdinLock<'a>) -> Self {
+        Commands { stdin, lines: VecDeque::new(), partial: String::new(), closed: false }
+    }
+
+    // Reads what stdin has, waiting for it if there's nothing yet.
+    fn fill(&mut self) -> io::Result<()> {
+        let buf = self.stdin.fill_buf()?;
+        let len = buf.len();
+        self.partial.push_str(&String::from_utf8_lossy(buf));
+        self.stdin.consume(len);
+
+        if len == 0 {
+            self.closed = true;
+            if !self.partial.is_empty() {
+                self.partial.push('\n');
+            }
+        }
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            self.lines.push_back(line.trim().to_owned());
+        }
+        Ok(())
+    }
+
+    // The next command, or `None` once stdin is closed.
+    fn next(&mut self) -> io::Result<Option<String>> {
+        while self.lines.is_empty() && !self.closed {
+            self.fill()?;
+        }
+        Ok(self.lines.pop_front())
+    }
+
+    // The next command if one arrives within `timeout`.
+    fn next_within(&mut self, timeout: Duration) -> io::Result<Option<String>> {
+        if self.lines.is_empty() {
+            if self.closed {
+                thread::sleep(timeout);
+            } else if ready(timeout)? {
+                self.fill()?;
+            }
+        }
+        Ok(self.lines.pop_front())
+    }
+}
+
+// Waits up to `timeout` for stdin to have something to read.
+#[cfg(unix)]
+fn ready(timeout: Duration) -> io::Result<bool> {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    match unsafe { libc::poll(&mut fd, 1, millis) } {
+        -1 => match io::Error::last_os_error() {
+            err if err.kind() == io::ErrorKind::Interrupted => Ok(false),
+            err => Err(err),
+        },
+        n => Ok(n > 0),
+    }
+}
+
+#[cfg(not(unix))]
+fn ready(timeout: Duration) -> io::Result<bool> {
+    thread::sleep(timeout);
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Turn;
+    use crate::robot::Robot;
+
+    // Paints the start white, moves right and paints black, then moves down.
+    fn robot() -> Robot {
+        let mut robot = Robot::new(Point::ORIGIN, Direction::Up);
+        robot.paint(1);
+        robot.turn(Turn::Right);
+        robot.forward();
+        robot.paint(0);
+        robot.turn(Turn::Right);
+        robot.forward();
+        robot
+    }
+
+    #[test]
+    fn frames_show_the_run_so_far() {
+        let robot = robot();
+        let replay = Replay::new(&robot.world);
+        assert_eq!(replay.frames(), 3);
+        assert_eq!(replay.frame(0), "step 0/2  painted 1\n^ \n  \n");
+        assert_eq!(replay.frame(1), "step 1/2  painted 2\n█>\n  \n");
+        assert_eq!(replay.frame(2), "step 2/2  painted 2\n█.\n v\n");
+    }
+
+    #[test]
+    fn dumps_every_frame() {
+        let robot = robot();
+        let replay = Replay::new(&robot.world);
+        let mut out = Vec::new();
+        replay.dump(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches('\x0c').count(), 3);
+        assert!(out.starts_with(&replay.frame(0)));
+    }
+}
@@ -0,0 +1,488 @@
+use std::error;
+use std::fmt;
+use std::iter;
+use std::ops::Range;
+
+use crate::image::{self, Image, Palette, RgbaImage};
+
+pub const BLACK: u8 = 0;
+pub const WHITE: u8 = 1;
+pub const TRANSPARENT: u8 = 2;
+
+/// Which colour digits an image may contain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mode {
+    /// Only black, white and transparent, as in the puzzle.
+    Standard,
+    /// Any digit, with 3 to 9 picking further colours from a palette.
+    Extended,
+}
+
+impl Mode {
+    pub fn max_color(self) -> u8 {
+        match self {
+            Mode::Standard => TRANSPARENT,
+            Mode::Extended => 9,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    EmptyDimensions,
+    /// The data doesn't split into whole layers of `width * height` digits.
+    PartialLayer { len: usize, layer_size: usize },
+    InvalidDigit { position: usize, found: char },
+    DimensionMismatch { expected: (usize, usize), found: (usize, usize) },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyDimensions =>
+                write!(f, "image width and height must be positive"),
+            Error::PartialLayer { len, layer_size } =>
+                write!(
+                    f,
+                    "{} digits is not a whole number of {} digit layers",
+                    len, layer_size,
+                ),
+            Error::InvalidDigit { position, found } =>
+                write!(f, "invalid colour {:?} at position {}", found, position),
+            Error::DimensionMismatch { expected, found } =>
+                write!(
+                    f,
+                    "expected a {}x{} image but found {}x{}",
+                    expected.0, expected.1, found.0, found.1,
+                ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// How many pixels of each colour a layer has.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LayerStats([usize; 10]);
+
+impl LayerStats {
+    pub fn count(&self, color: u8) -> usize {
+        self.0.get(color as usize).copied().unwrap_or(0)
+    }
+
+    /// The count for every colour digit, indexed by the digit.
+    pub fn histogram(&self) -> &[usize; 10] {
+        &self.0
+    }
+}
+
+fn stack(layers: &[Vec<u8>], size: usize) -> Vec<u8> {
+    let mut pixels = vec![TRANSPARENT; size];
+    for layer in layers.iter().rev() {
+        pixels
+            .iter_mut()
+            .zip(layer)
+            .filter(|&(_, &color)| color != TRANSPARENT)
+            .for_each(|(pixel, &color)| *pixel = color);
+    }
+    pixels
+}
+
+/// An image in the Space Image Format: a stack of layers of colour digits,
+/// the first layer in front.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpaceImage {
+    width: usize,
+    height: usize,
+    mode: Mode,
+    layers: Vec<Vec<u8>>,
+}
+
+impl SpaceImage {
+    pub fn decode(data: &str, width: usize, height: usize) -> Result<Self, Error> {
+        SpaceImage::decode_with(data, width, height, Mode::Standard)
+    }
+
+    pub fn decode_with(
+        data: &str,
+        width: usize,
+        height: usize,
+        mode: Mode,
+    ) -> Result<Self, Error> {
+        let layer_size = width * height;
+        if layer_size == 0 {
+            return Err(Error::EmptyDimensions);
+        }
+
+        let data = data.trim_end();
+        let digits = data
+            .chars()
+            .enumerate()
+            .map(|(position, found)| match found.to_digit(10) {
+                Some(digit) if digit <= mode.max_color() as u32 => Ok(digit as u8),
+                _ => Err(Error::InvalidDigit { position, found }),
+            })
+            .collect::<Result<Vec<u8>, Error>>()?;
+
+        if digits.is_empty() || digits.len() % layer_size != 0 {
+            return Err(Error::PartialLayer { len: digits.len(), layer_size });
+        }
+
+        Ok(SpaceImage {
+            width,
+            height,
+            mode,
+            layers: digits.chunks(layer_size).map(Vec::from).collect(),
+        })
+    }
+
+    pub fn from_layers(
+        width: usize,
+        height: usize,
+        layers: Vec<Vec<u8>>,
+    ) -> Result<Self, Error> {
+        SpaceImage::from_layers_with(width, height, layers, Mode::Standard)
+    }
+
+    pub fn from_layers_with(
+        width: usize,
+        height: usize,
+        layers: Vec<Vec<u8>>,
+        mode: Mode,
+    ) -> Result<Self, Error> {
+        let layer_size = width * height;
+        if layer_size == 0 {
+            return Err(Error::EmptyDimensions);
+        }
+
+        let mut position = 0;
+        for layer in &layers {
+            if layer.len() != layer_size {
+                return Err(Error::PartialLayer { len: position + layer.len(), layer_size });
+            }
+            if let Some(offset) = layer.iter().position(|&color| color > mode.max_color()) {
+                return Err(Error::InvalidDigit {
+                    position: position + offset,
+                    found: std::char::from_digit(layer[offset] as u32, 10).unwrap_or('?'),
+                });
+            }
+            position += layer_size;
+        }
+        if layers.is_empty() {
+            return Err(Error::PartialLayer { len: 0, layer_size });
+        }
+
+        Ok(SpaceImage { width, height, mode, layers })
+    }
+
+    /// Spreads an image over `count` layers: each visible pixel is put on one
+    /// of the layers in turn, with transparency in front of it and filler
+    /// behind it, so that only compositing recovers the image. Images using
+    /// colours past transparent give an extended image.
+    pub fn from_image(image: &Image, count: usize) -> Result<Self, Error> {
+        let count = count.max(1);
+        let layers = (0..count)
+            .map(|layer| {
+                image.pixels
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &color)| {
+                        let home = i % count;
+                        if color == TRANSPARENT || layer < home {
+                            TRANSPARENT
+                        } else if layer == home {
+                            color
+                        } else {
+                            ((i + layer) % 2) as u8
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mode = if image.pixels.iter().any(|&color| color > TRANSPARENT) {
+            Mode::Extended
+        } else {
+            Mode::Standard
+        };
+        SpaceImage::from_layers_with(image.width, image.height, layers, mode)
+    }
+
+    /// The image as a string of digits, which `decode_with` reads back in
+    /// the image's mode. Only standard images can be read with `decode`.
+    pub fn encode(&self) -> String {
+        self.layers
+            .iter()
+            .flatten()
+            .map(|&color| (b'0' + color) as char)
+            .collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn layers(&self) -> &[Vec<u8>] {
+        &self.layers
+    }
+
+    pub fn layer_stats(&self) -> Vec<LayerStats> {
+        self.layers
+            .iter()
+            .map(|layer| {
+                let mut counts = [0; 10];
+                layer.iter().for_each(|&color| counts[color as usize] += 1);
+                LayerStats(counts)
+            })
+            .collect()
+    }
+
+    pub fn layer_image(&self, layer: usize) -> Image {
+        Image::new(self.width, self.height, self.layers[layer].clone())
+    }
+
+    /// Stacks the layers, keeping the first pixel from the front that isn't
+    /// transparent. Pixels transparent on every layer stay transparent.
+    ///
+    /// This works on colour digits, so every digit but `TRANSPARENT` hides
+    /// what's behind it, whatever its palette colour's alpha.
+    pub fn composite(&self) -> Image {
+        Image::new(
+            self.width,
+            self.height,
+            stack(&self.layers, self.width * self.height),
+        )
+    }
+
+    /// Looks every digit up in `palette` and lays the layers over each
+    /// other from the back, so translucent colours tint what's behind them.
+    pub fn composite_rgba(&self, palette: &Palette) -> RgbaImage {
+        let mut pixels = vec![[0, 0, 0, 0]; self.width * self.height];
+        for layer in self.layers.iter().rev() {
+            for (pixel, &color) in pixels.iter_mut().zip(layer) {
+                *pixel = image::blend(palette.color(color), *pixel);
+            }
+        }
+        RgbaImage { width: self.width, height: self.height, pixels }
+    }
+
+    /// Puts the layers of `other` behind this image's layers.
+    pub fn merge(&self, other: &SpaceImage) -> Result<SpaceImage, Error> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(Error::DimensionMismatch {
+                expected: (self.width, self.height),
+                found: (other.width, other.height),
+            });
+        }
+
+        let mut merged = self.clone();
+        merged.mode = self.mode.max(other.mode);
+        merged.layers.extend(other.layers.iter().cloned());
+        Ok(merged)
+    }
+
+    /// Replaces a range of layers with the single layer they composite to.
+    pub fn flatten_layers(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let layer = stack(&self.layers[range.clone()], self.width * self.height);
+        self.layers.splice(range, iter::once(layer));
+    }
+
+    pub fn flatten(&self) -> SpaceImage {
+        let mut flat = self.clone();
+        flat.flatten_layers(0 .. flat.layers.len());
+        flat
+    }
+
+    /// Drops the layers that don't show through anywhere, keeping the rest
+    /// as they are. The composite is unchanged.
+    pub fn optimise(&self) -> SpaceImage {
+        let size = self.width * self.height;
+        let mut covered = vec![false; size];
+        let mut layers: Vec<Vec<u8>> = self.layers
+            .iter()
+            .filter(|layer| {
+                let mut visible = false;
+                for (covered, &color) in covered.iter_mut().zip(layer.iter()) {
+                    if !*covered && color != TRANSPARENT {
+                        *covered = true;
+                        visible = true;
+                    }
+                }
+                visible
+            })
+            .cloned()
+            .collect();
+
+        if layers.is_empty() {
+            layers.push(vec![TRANSPARENT; size]);
+        }
+
+        SpaceImage { layers, ..*self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composites_the_example() {
+        let image = SpaceImage::decode("0222112222120000\n", 2, 2).unwrap();
+        assert_eq!(image.layers().len(), 4);
+        assert_eq!(image.layers()[1], vec![1, 1, 2, 2]);
+        assert_eq!(image.composite().pixels, vec![0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn counts_colours_per_layer() {
+        let image = SpaceImage::decode("001122012012", 3, 2).unwrap();
+        let stats = image.layer_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].count(BLACK), stats[0].count(WHITE), stats[0].count(TRANSPARENT)),
+            (2, 2, 2),
+        );
+        assert_eq!(stats[1].count(9), 0);
+    }
+
+    #[test]
+    fn rejects_partial_layers() {
+        assert_eq!(
+            SpaceImage::decode("02221", 2, 2),
+            Err(Error::PartialLayer { len: 5, layer_size: 4 }),
+        );
+        assert_eq!(
+            SpaceImage::decode("", 2, 2),
+            Err(Error::PartialLayer { len: 0, layer_size: 4 }),
+        );
+        assert_eq!(SpaceImage::decode("0", 0, 2), Err(Error::EmptyDimensions));
+    }
+
+    #[test]
+    fn rejects_invalid_digits() {
+        assert_eq!(
+            SpaceImage::decode("0221x222", 2, 2),
+            Err(Error::InvalidDigit { position: 4, found: 'x' }),
+        );
+        assert_eq!(
+            SpaceImage::decode("123456789012", 3, 2),
+            Err(Error::InvalidDigit { position: 2, found: '3' }),
+        );
+        assert_eq!(
+            SpaceImage::decode("0222\n1222", 2, 2),
+            Err(Error::InvalidDigit { position: 4, found: '\n' }),
+        );
+    }
+
+    // A 5x3 picture with transparent pixels scattered through it.
+    fn picture() -> Image {
+        Image::new(5, 3, vec![
+            0, 1, 2, 1, 0,
+            1, 2, 2, 0, 1,
+            2, 0, 1, 1, 2,
+        ])
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let image = picture();
+        for count in 1..=6 {
+            let encoded = SpaceImage::from_image(&image, count).unwrap();
+            assert_eq!(encoded.layers().len(), count);
+            assert_eq!(encoded.mode(), Mode::Standard);
+
+            let decoded = SpaceImage::decode(&encoded.encode(), 5, 3).unwrap();
+            assert_eq!(decoded, encoded);
+            assert_eq!(decoded.composite(), image);
+        }
+
+        let blank = Image::new(2, 2, vec![TRANSPARENT; 4]);
+        let encoded = SpaceImage::from_image(&blank, 3).unwrap().encode();
+        assert_eq!(SpaceImage::decode(&encoded, 2, 2).unwrap().composite(), blank);
+    }
+
+    #[test]
+    fn layer_tools_keep_the_composite() {
+        let image = picture();
+        for count in 1..=6 {
+            let encoded = SpaceImage::from_image(&image, count).unwrap();
+
+            let flat = encoded.flatten();
+            assert_eq!(flat.layers().len(), 1);
+            assert_eq!(flat.composite(), image);
+
+            let optimised = encoded.optimise();
+            assert!(optimised.layers().len() <= count);
+            assert_eq!(optimised.composite(), image);
+
+            let mut partly = encoded.clone();
+            partly.flatten_layers(1..count);
+            assert_eq!(partly.composite(), image);
+        }
+
+        // Layers hidden behind opaque ones are dropped.
+        let hidden = SpaceImage::decode("011010012222", 2, 2).unwrap();
+        assert_eq!(hidden.optimise().layers(), &[vec![0, 1, 1, 0]]);
+    }
+
+    #[test]
+    fn merges_behind() {
+        let front = SpaceImage::decode("2120", 2, 2).unwrap();
+        let back = SpaceImage::decode("0111", 2, 2).unwrap();
+        let merged = front.merge(&back).unwrap();
+        assert_eq!(merged.layers().len(), 2);
+        assert_eq!(merged.composite().pixels, vec![0, 1, 1, 0]);
+
+        let other = SpaceImage::decode("012", 3, 1).unwrap();
+        assert_eq!(
+            front.merge(&other),
+            Err(Error::DimensionMismatch { expected: (2, 2), found: (3, 1) }),
+        );
+    }
+
+    #[test]
+    fn extended_images_round_trip() {
+        let image = Image::new(2, 2, vec![3, 1, 0, 2]);
+        let encoded = SpaceImage::from_image(&image, 3).unwrap();
+        assert_eq!(encoded.mode(), Mode::Extended);
+
+        let data = encoded.encode();
+        assert_eq!(data, "322211220102");
+        assert_eq!(
+            SpaceImage::decode(&data, 2, 2),
+            Err(Error::InvalidDigit { position: 0, found: '3' }),
+        );
+        let decoded = SpaceImage::decode_with(&data, 2, 2, Mode::Extended).unwrap();
+        assert_eq!(decoded, encoded);
+        assert_eq!(decoded.composite(), image);
+    }
+
+    #[test]
+    fn blends_translucent_colours() {
+        let image = SpaceImage::decode_with("72381222", 4, 1, Mode::Extended).unwrap();
+        assert_eq!(image.composite().pixels, vec![7, 2, 3, 8]);
+
+        let rgba = image.composite_rgba(&Palette::extended());
+        assert_eq!(rgba.pixels, vec![
+            // Half red over white.
+            [255, 127, 127, 255],
+            // Transparent all the way through.
+            [0, 0, 0, 0],
+            // Opaque in front hides the back.
+            [230, 57, 70, 255],
+            // Half green over nothing stays half green.
+            [0, 255, 0, 128],
+        ]);
+    }
+}
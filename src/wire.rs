@@ -0,0 +1,290 @@
+//! Wires laid out as straight runs on a grid, as on the fuel management
+//! system's front panel. Wires are kept as segments rather than as the points
+//! they pass through, so a run of a million steps costs no more than a run of
+//! one.
+
+use std::error;
+use std::fmt;
+
+use crate::geometry::{Direction, Point};
+
+mod circuit;
+mod diagram;
+
+pub use circuit::{Circuit, Junction, PairReport, Report, WireCrossing};
+pub use diagram::{svg, DiagramOptions};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The move at `index` in the wire's list on `line` isn't a direction
+    /// letter followed by a distance.
+    InvalidMove { line: usize, index: usize, text: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidMove { line, index, text } =>
+                write!(
+                    f,
+                    "line {}: invalid move {:?} at position {}",
+                    line, text, index,
+                ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// A straight run of wire. `steps` is how far along the wire `start` is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+    pub steps: i64,
+}
+
+impl Segment {
+    pub fn length(&self) -> i64 {
+        self.start.distance(self.end)
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    /// How far along the wire `point` is, which must be on the segment.
+    pub fn steps_to(&self, point: Point) -> i64 {
+        self.steps + self.start.distance(point)
+    }
+
+    // The coordinate that stays the same along the segment, and the range
+    // covered by the other one.
+    fn line(&self) -> (i64, i64, i64) {
+        if self.is_horizontal() {
+            (self.start.y, self.start.x.min(self.end.x), self.start.x.max(self.end.x))
+        } else {
+            (self.start.x, self.start.y.min(self.end.y), self.start.y.max(self.end.y))
+        }
+    }
+
+    fn point(&self, along: i64, across: i64) -> Point {
+        if self.is_horizontal() {
+            Point::new(along, across)
+        } else {
+            Point::new(across, along)
+        }
+    }
+
+    /// The points where the segments meet. Where they run along each other
+    /// only the ends of the shared stretch and its points closest to the
+    /// origin are given, which are the only places either metric can be
+    /// smallest. The points either side of the origin are included in case
+    /// the origin itself is left out.
+    pub fn meets(&self, other: &Segment) -> Vec<Point> {
+        let (across, lo, hi) = self.line();
+        let (other_across, other_lo, other_hi) = other.line();
+
+        if self.is_horizontal() != other.is_horizontal() {
+            let crossed = (lo ..= hi).contains(&other_across)
+                && (other_lo ..= other_hi).contains(&across);
+            return if crossed {
+                vec![self.point(other_across, across)]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let (lo, hi) = (lo.max(other_lo), hi.min(other_hi));
+        if across != other_across || lo > hi {
+            return Vec::new();
+        }
+        let mut points: Vec<i64> = [lo, -1, 0, 1, hi]
+            .iter()
+            .map(|&along| along.max(lo).min(hi))
+            .collect();
+        points.dedup();
+        points.into_iter().map(|along| self.point(along, across)).collect()
+    }
+}
+
+/// How to rank crossings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// Distance from the central port.
+    Manhattan,
+    /// Steps taken along the wires to get there.
+    Delay,
+}
+
+/// A point where two wires meet, and how far along each wire it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Crossing {
+    pub point: Point,
+    pub steps: (i64, i64),
+}
+
+impl Crossing {
+    /// The Manhattan distance from the central port.
+    pub fn manhattan(&self) -> i64 {
+        self.point.manhattan()
+    }
+
+    /// The combined steps both wires take to reach the crossing.
+    pub fn delay(&self) -> i64 {
+        self.steps.0 + self.steps.1
+    }
+
+    pub fn measure(&self, metric: Metric) -> i64 {
+        match metric {
+            Metric::Manhattan => self.manhattan(),
+            Metric::Delay => self.delay(),
+        }
+    }
+}
+
+// The segments of a wire sorted so that the ones a segment might meet can be
+// found by binary search.
+struct SegmentIndex<'a> {
+    horizontal: Vec<&'a Segment>,
+    vertical: Vec<&'a Segment>,
+}
+
+impl<'a> SegmentIndex<'a> {
+    fn new(segments: &'a [Segment]) -> Self {
+        let (mut horizontal, mut vertical): (Vec<&Segment>, Vec<_>) = segments
+            .iter()
+            .partition(|segment| segment.is_horizontal());
+        horizontal.sort_by_key(|segment| segment.line());
+        vertical.sort_by_key(|segment| segment.line());
+        SegmentIndex { horizontal, vertical }
+    }
+
+    fn candidates(&self, segment: &Segment) -> impl Iterator<Item = &'a Segment> + '_ {
+        let (across, lo, hi) = segment.line();
+        let (parallel, perpendicular) = if segment.is_horizontal() {
+            (&self.horizontal, &self.vertical)
+        } else {
+            (&self.vertical, &self.horizontal)
+        };
+
+        let start = parallel.partition_point(|other| other.line().0 < across);
+        let end = parallel.partition_point(|other| other.line().0 <= across);
+        let first = perpendicular.partition_point(|other| other.line().0 < lo);
+        let last = perpendicular.partition_point(|other| other.line().0 <= hi);
+
+        parallel[start..end].iter().chain(&perpendicular[first..last]).copied()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Wire {
+    segments: Vec<Segment>,
+}
+
+impl Wire {
+    /// Reads a comma separated list of moves such as `R8,U5,L5,D3`.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        Wire::parse_line(text, 1)
+    }
+
+    fn parse_line(text: &str, line: usize) -> Result<Self, Error> {
+        let moves = text
+            .trim()
+            .split(',')
+            .enumerate()
+            .map(|(index, text)| {
+                let mut chars = text.chars();
+                chars
+                    .next()
+                    .and_then(Direction::from_letter)
+                    .zip(chars.as_str().parse().ok())
+                    .filter(|&(_, distance)| distance >= 0)
+                    .ok_or_else(|| Error::InvalidMove { line, index, text: text.to_string() })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Wire::from_moves(moves))
+    }
+
+    /// Lays out a wire from the origin. Moves of no distance are dropped.
+    pub fn from_moves<I>(moves: I) -> Self
+    where
+        I: IntoIterator<Item = (Direction, i64)>,
+    {
+        let mut start = Point::ORIGIN;
+        let mut steps = 0;
+        let segments = moves
+            .into_iter()
+            .filter(|&(_, distance)| distance > 0)
+            .map(|(direction, distance)| {
+                let end = start + direction.delta() * distance;
+                let segment = Segment { start, end, steps };
+                start = end;
+                steps += distance;
+                segment
+            })
+            .collect();
+
+        Wire { segments }
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// The total number of steps along the wire.
+    pub fn len(&self) -> i64 {
+        self.segments.last().map_or(0, |last| last.steps + last.length())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// How many steps along the wire it first reaches `point`, if it does.
+    pub fn steps_to(&self, point: Point) -> Option<i64> {
+        self.segments
+            .iter()
+            .find(|segment| {
+                segment.start.distance(point) + segment.end.distance(point)
+                    == segment.length()
+            })
+            .map(|segment| segment.steps_to(point))
+    }
+
+    /// Every place the wires meet apart from the origin, once for each pair
+    /// of visits.
+    pub fn crossings(&self, other: &Wire) -> Vec<Crossing> {
+        let mut crossings = self.meetings(other);
+        crossings.retain(|crossing| crossing.point != Point::ORIGIN);
+        crossings
+    }
+
+    /// The places the wire runs into itself. `steps` gives the earlier visit
+    /// first.
+    pub fn self_crossings(&self) -> Vec<Crossing> {
+        let mut crossings = self.meetings(self);
+        crossings.retain(|crossing| crossing.steps.0 < crossing.steps.1);
+        crossings
+    }
+
+    fn meetings(&self, other: &Wire) -> Vec<Crossing> {
+        let index = SegmentIndex::new(&other.segments);
+        let mut crossings: Vec<Crossing> = self.segments
+            .iter()
+            .flat_map(|segment| {
+                index.candidates(segment).flat_map(move |other| {
+                    segment.meets(other).into_iter().map(move |point| Crossing {
+                        point,
+                        steps: (segment.steps_to(point), other.steps_to(point)),
+                    })
+                })
+            })
+            .collect();
+
+        crossings.sort_unstable();
+        crossings.dedup();
+        crossings
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{Crossing, Error, Metric, Wire};
+use crate::geometry::Point;
+
+/// A crossing between two of a circuit's wires, given by their indices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireCrossing {
+    pub wires: (usize, usize),
+    pub crossing: Crossing,
+}
+
+/// A point where several wires meet, with how far along each of them it
+/// first is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Junction {
+    pub point: Point,
+    pub wires: Vec<(usize, i64)>,
+}
+
+impl Junction {
+    pub fn delay(&self) -> i64 {
+        self.wires.iter().map(|&(_, steps)| steps).sum()
+    }
+}
+
+/// Any number of wires starting from the same central port.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Circuit {
+    wires: Vec<Wire>,
+}
+
+impl Circuit {
+    pub fn new(wires: Vec<Wire>) -> Self {
+        Circuit { wires }
+    }
+
+    /// Reads one wire per line, skipping blank lines.
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let wires = input
+            .lines()
+            .zip(1..)
+            .filter(|(text, _)| !text.trim().is_empty())
+            .map(|(text, line)| Wire::parse_line(text, line))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Circuit { wires })
+    }
+
+    pub fn wires(&self) -> &[Wire] {
+        &self.wires
+    }
+
+    /// The crossings of every pair of wires.
+    pub fn crossings(&self) -> Vec<WireCrossing> {
+        let mut crossings = Vec::new();
+        for (i, first) in self.wires.iter().enumerate() {
+            for (j, second) in self.wires.iter().enumerate().skip(i + 1) {
+                crossings.extend(
+                    first
+                        .crossings(second)
+                        .into_iter()
+                        .map(|crossing| WireCrossing { wires: (i, j), crossing }),
+                );
+            }
+        }
+        crossings
+    }
+
+    pub fn closest(&self, metric: Metric) -> Option<WireCrossing> {
+        self.crossings()
+            .into_iter()
+            .min_by_key(|crossing| crossing.crossing.measure(metric))
+    }
+
+    /// The points where at least `count` wires meet, for a `count` of two or
+    /// more. Where wires run along each other, only the points
+    /// `Segment::meets` picks out are considered.
+    pub fn junctions(&self, count: usize) -> Vec<Junction> {
+        let points: BTreeSet<Point> = self.crossings()
+            .into_iter()
+            .map(|crossing| crossing.crossing.point)
+            .collect();
+
+        points
+            .into_iter()
+            .map(|point| Junction {
+                point,
+                wires: self.wires
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, wire)| wire.steps_to(point).map(|steps| (i, steps)))
+                    .collect(),
+            })
+            .filter(|junction| junction.wires.len() >= count)
+            .collect()
+    }
+
+    /// Gathers which wires cross where, for checking a layout.
+    pub fn report(&self) -> Report {
+        let mut pairs = Vec::new();
+        for (i, first) in self.wires.iter().enumerate() {
+            for (j, second) in self.wires.iter().enumerate().skip(i + 1) {
+                let crossings = first.crossings(second);
+                let best = |metric| {
+                    crossings.iter().copied().min_by_key(|crossing| crossing.measure(metric))
+                };
+                pairs.push(PairReport {
+                    wires: (i, j),
+                    crossings: crossings.len(),
+                    closest: best(Metric::Manhattan),
+                    quickest: best(Metric::Delay),
+                });
+            }
+        }
+
+        Report {
+            lengths: self.wires.iter().map(Wire::len).collect(),
+            self_crossings: self.wires.iter().map(Wire::self_crossings).collect(),
+            pairs,
+            junctions: self.junctions(3),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PairReport {
+    pub wires: (usize, usize),
+    pub crossings: usize,
+    pub closest: Option<Crossing>,
+    pub quickest: Option<Crossing>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// The steps along each wire.
+    pub lengths: Vec<i64>,
+    pub self_crossings: Vec<Vec<Crossing>>,
+    pub pairs: Vec<PairReport>,
+    /// The points where three or more wires meet.
+    pub junctions: Vec<Junction>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (length, crossings)) in self.lengths.iter().zip(&self.self_crossings).enumerate() {
+            writeln!(
+                f,
+                "wire {}: {} steps, {} self-crossings",
+                i, length, crossings.len(),
+            )?;
+            for crossing in crossings {
+                writeln!(
+                    f,
+                    "  crosses itself at {} after {} and {} steps",
+                    crossing.point, crossing.steps.0, crossing.steps.1,
+                )?;
+            }
+        }
+
+        for pair in &self.pairs {
+            writeln!(
+                f,
+                "wires {} and {}: {} crossings",
+                pair.wires.0, pair.wires.1, pair.crossings,
+            )?;
+            if let Some(closest) = pair.closest {
+                writeln!(
+                    f,
+                    "  closest at {}, distance {}",
+                    closest.point, closest.manhattan(),
+                )?;
+            }
+            if let Some(quickest) = pair.quickest {
+                writeln!(
+                    f,
+                    "  quickest at {}, {} + {} = {} steps",
+                    quickest.point, quickest.steps.0, quickest.steps.1, quickest.delay(),
+                )?;
+            }
+        }
+
+        for junction in &self.junctions {
+            write!(f, "junction at {}:", junction.point)?;
+            for &(wire, steps) in &junction.wires {
+                write!(f, " wire {} after {},", wire, steps)?;
+            }
+            writeln!(f, " {} steps in all", junction.delay())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use super::{Circuit, Metric, WireCrossing};
+use crate::geometry::Point;
+use crate::grid::Bounds;
+
+const COLORS: &[&str] = &[
+    "#e63946", "#1d7fd6", "#2a9d8f", "#f4a261", "#8338ec", "#6a994e",
+];
+
+#[derive(Clone, Debug)]
+pub struct DiagramOptions {
+    /// The length, in output pixels, of the longer side of the layout.
+    pub size: f64,
+    /// Blank space around the layout, in output pixels.
+    pub margin: f64,
+}
+
+impl Default for DiagramOptions {
+    fn default() -> Self {
+        DiagramOptions { size: 800.0, margin: 40.0 }
+    }
+}
+
+// Maps layout positions to output pixels.
+struct Transform {
+    bounds: Bounds,
+    scale: f64,
+    margin: f64,
+}
+
+impl Transform {
+    fn apply(&self, point: Point) -> (f64, f64) {
+        (
+            (point.x - self.bounds.x1) as f64 * self.scale + self.margin,
+            (point.y - self.bounds.y1) as f64 * self.scale + self.margin,
+        )
+    }
+}
+
+fn label(out: &mut String, (x, y): (f64, f64), dy: f64, text: &str) {
+    let _ = writeln!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" font-family="monospace" font-size="12">{}</text>"#,
+        x + 8.0, y + dy, text,
+    );
+}
+
+/// Draws the circuit as SVG, scaled to fit `options.size`. Each wire is a
+/// polyline from the central port, every crossing is circled, and the
+/// crossings closest to the port and with the shortest delay are filled in
+/// and labelled.
+pub fn svg(circuit: &Circuit, options: &DiagramOptions) -> String {
+    let corners = circuit.wires()
+        .iter()
+        .flat_map(|wire| wire.segments().iter().map(|segment| segment.end));
+    let bounds = Bounds::of(corners).map_or(Bounds::point(Point::ORIGIN), |bounds| {
+        bounds.extend(Point::ORIGIN)
+    });
+    let longest = bounds.width().max(bounds.height()).max(2) - 1;
+    let transform = Transform {
+        bounds,
+        scale: options.size / longest as f64,
+        margin: options.margin,
+    };
+
+    let (width, height) = (
+        (bounds.width() - 1) as f64 * transform.scale + options.margin * 2.0,
+        (bounds.height() - 1) as f64 * transform.scale + options.margin * 2.0,
+    );
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0:.0}" height="{1:.0}" viewBox="0 0 {0:.1} {1:.1}">"#,
+        width, height,
+    );
+
+    for (i, wire) in circuit.wires().iter().enumerate() {
+        let mut points = vec![transform.apply(Point::ORIGIN)];
+        points.extend(wire.segments().iter().map(|segment| transform.apply(segment.end)));
+        let points: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect();
+        let _ = writeln!(
+            out,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" stroke-opacity="0.8"><title>wire {}</title></polyline>"#,
+            points.join(" "), COLORS[i % COLORS.len()], i,
+        );
+    }
+
+    let crossings = circuit.crossings();
+    for WireCrossing { wires, crossing } in &crossings {
+        let (x, y) = transform.apply(crossing.point);
+        let _ = writeln!(
+            out,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="4" fill="none" stroke="black"><title>wires {} and {} at {}</title></circle>"#,
+            x, y, wires.0, wires.1, crossing.point,
+        );
+    }
+
+    let port = transform.apply(Point::ORIGIN);
+    let _ = writeln!(
+        out,
+        r#"<rect x="{:.1}" y="{:.1}" width="8" height="8" fill="black"/>"#,
+        port.0 - 4.0, port.1 - 4.0,
+    );
+    label(&mut out, port, -8.0, "port");
+
+    let best = [
+        (Metric::Manhattan, circuit.closest(Metric::Manhattan), 4.0),
+        (Metric::Delay, circuit.closest(Metric::Delay), 18.0),
+    ];
+    for &(metric, best, dy) in &best {
+        if let Some(WireCrossing { crossing, .. }) = best {
+            let at = transform.apply(crossing.point);
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="6" fill="black"/>"#,
+                at.0, at.1,
+            );
+            let text = match metric {
+                Metric::Manhattan => format!("closest: distance {}", crossing.manhattan()),
+                Metric::Delay => format!(
+                    "quickest: {} + {} = {} steps",
+                    crossing.steps.0, crossing.steps.1, crossing.delay(),
+                ),
+            };
+            label(&mut out, at, dy, &text);
+        }
+    }
+
+    out.push_str("</svg>\n");
+    out
+}